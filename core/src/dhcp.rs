@@ -0,0 +1,446 @@
+use dhcparse::dhcpv4::{Addr, DhcpOption, Encode, Encoder, Message, MessageType, OpCode};
+use dhcparse::v4_options;
+
+//配网热点的 DHCP 服务，地址池 192.168.2.10 - 192.168.2.20，按客户端 MAC 分配
+//handle 只处理报文和租约表，不涉及网络收发，时间由调用者传入
+pub const DHCP_PACKET_SIZE:usize = 512;
+pub const SERVER_IP:[u8;4] = [192,168,2,1];
+const SUBNET_MASK:[u8;4] = [255,255,255,0];
+const POOL_START:[u8;4] = [192,168,2,10];
+const POOL_SIZE:usize = 11;
+const LEASE_SECS:u32 = 3600;
+const OFFER_SECS:u64 = 60;//发出 OFFER 后保留地址的时间
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LeaseState{
+    Offered,
+    Bound,
+    Declined,//客户端发现地址被占用，暂时不再分配
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Lease{
+    mac:[u8;6],
+    state:LeaseState,
+    expires:u64,//秒
+}
+
+//回复的发送方式，还没有地址的客户端只能广播
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DhcpReply{
+    Broadcast,
+    Unicast([u8;4]),
+}
+
+pub struct DhcpServer{
+    leases:[Option<Lease>;POOL_SIZE],//下标对应地址池中的地址
+}
+
+impl Default for DhcpServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DhcpServer {
+    pub const fn new()->Self{
+        Self{ leases: [None;POOL_SIZE] }
+    }
+
+    fn addr(index:usize)->[u8;4]{
+        let mut addr = POOL_START;
+        addr[3] += index as u8;
+        addr
+    }
+
+    fn index_of(addr:[u8;4])->Option<usize>{
+        if addr[..3] != POOL_START[..3] || addr[3] < POOL_START[3] {
+            return None;
+        }
+        let index = (addr[3] - POOL_START[3]) as usize;
+        if index < POOL_SIZE { Some(index) } else { None }
+    }
+
+    fn find(&self,mac:&[u8;6])->Option<usize>{
+        self.leases.iter().position(|v| matches!(v, Some(lease) if lease.mac == *mac && lease.state != LeaseState::Declined))
+    }
+
+    //地址没有租约，或租约已过期
+    fn is_free(&self,index:usize,now:u64)->bool{
+        match self.leases[index] {
+            Some(lease) => lease.expires <= now,
+            None => true,
+        }
+    }
+
+    //优先使用客户端之前的地址，其次是客户端请求的地址，最后是第一个空闲地址
+    fn choose(&self,mac:&[u8;6],requested:Option<[u8;4]>,now:u64)->Option<usize>{
+        if let Some(index) = self.find(mac) {
+            return Some(index);
+        }
+        if let Some(index) = requested.and_then(Self::index_of) {
+            if self.is_free(index, now) {
+                return Some(index);
+            }
+        }
+        (0..POOL_SIZE).find(|index| self.is_free(*index, now))
+    }
+
+    //处理一个请求，需要回复时把回复写入 response 并返回发送方式
+    pub fn handle<T:AsRef<[u8]>>(&mut self,request:&Message<T>,now:u64,response:&mut [u8;DHCP_PACKET_SIZE])->Option<DhcpReply>{
+        if request.op().ok()? != OpCode::BootRequest {
+            return None;
+        }
+        let (msg_type,server_id,requested) = v4_options!(request; MessageType required, ServerIdentifier, RequestedIpAddress).ok()?;
+        let chaddr = request.chaddr().ok()?;
+        let mac:[u8;6] = chaddr.get(..6)?.try_into().ok()?;
+        let requested = requested.map(|v| v.0);
+        let ciaddr = request.ciaddr().0;
+
+        if msg_type == MessageType::DISCOVER {
+            let index = self.choose(&mac, requested, now)?;
+            self.leases[index] = Some(Lease{ mac, state: LeaseState::Offered, expires: now + OFFER_SECS });
+            Self::reply(request, MessageType::OFFER, Self::addr(index), response)?;
+            Some(DhcpReply::Broadcast)
+        } else if msg_type == MessageType::REQUEST {
+            //客户端选择了其他服务器
+            if let Some(server_id) = server_id {
+                if server_id.0 != SERVER_IP {
+                    if let Some(index) = self.find(&mac) {
+                        if self.leases[index].is_some_and(|v| v.state == LeaseState::Offered) {
+                            self.leases[index] = None;
+                        }
+                    }
+                    return None;
+                }
+            }
+            //续租时没有请求地址的选项，使用 ciaddr
+            let addr = requested.unwrap_or(ciaddr);
+            let index = Self::index_of(addr).filter(|index| {
+                match self.leases[*index] {
+                    Some(lease) => (lease.mac == mac && lease.state != LeaseState::Declined) || lease.expires <= now,
+                    None => true,
+                }
+            });
+            match index {
+                Some(index) => {
+                    //同一个客户端只保留一个地址
+                    if let Some(old) = self.find(&mac) {
+                        if old != index {
+                            self.leases[old] = None;
+                        }
+                    }
+                    self.leases[index] = Some(Lease{ mac, state: LeaseState::Bound, expires: now + LEASE_SECS as u64 });
+                    Self::reply(request, MessageType::ACK, addr, response)?;
+                }
+                None => {
+                    Self::reply(request, MessageType::NAK, [0;4], response)?;
+                }
+            }
+            Some(DhcpReply::Broadcast)
+        } else if msg_type == MessageType::RELEASE {
+            if let Some(index) = self.find(&mac) {
+                if Self::addr(index) == ciaddr {
+                    self.leases[index] = None;
+                }
+            }
+            None
+        } else if msg_type == MessageType::DECLINE {
+            //只接受持有这个地址的客户端的 DECLINE，避免其他客户端收回正在使用的地址
+            if let Some(index) = requested.and_then(Self::index_of) {
+                if self.find(&mac) == Some(index) {
+                    self.leases[index] = Some(Lease{ mac: [0;6], state: LeaseState::Declined, expires: now + LEASE_SECS as u64 });
+                }
+            }
+            None
+        } else if msg_type == MessageType::INFORM {
+            //客户端已有地址，只需要网络参数
+            Self::reply(request, MessageType::ACK, [0;4], response)?;
+            if ciaddr == [0;4] {
+                Some(DhcpReply::Broadcast)
+            } else {
+                Some(DhcpReply::Unicast(ciaddr))
+            }
+        } else {
+            None
+        }
+    }
+
+    //构建回复，INFORM 的回复不带租期，NAK 只带服务器标识
+    fn reply<T:AsRef<[u8]>>(request:&Message<T>,msg_type:MessageType,yiaddr:[u8;4],response:&mut [u8;DHCP_PACKET_SIZE])->Option<()>{
+        let server_ip = Addr(SERVER_IP);
+        let subnet_mask = Addr(SUBNET_MASK);
+        let inform = yiaddr == [0;4];
+        response.fill(0);
+
+        let mut msg = if msg_type == MessageType::NAK {
+            Encoder
+                .append_options([DhcpOption::MessageType(msg_type)])
+                .append_options([DhcpOption::ServerIdentifier(&server_ip)])
+                .encode(&Message::default(), response).ok()?
+        } else if inform {
+            Encoder
+                .append_options([DhcpOption::MessageType(msg_type)])
+                .append_options([DhcpOption::ServerIdentifier(&server_ip)])
+                .append_options([DhcpOption::SubnetMask(&subnet_mask)])
+                .append_options([DhcpOption::Router(&[server_ip])])
+                .append_options([DhcpOption::DomainNameServer(&[server_ip])])
+                .encode(&Message::default(), response).ok()?
+        } else {
+            Encoder
+                .append_options([DhcpOption::MessageType(msg_type)])
+                .append_options([DhcpOption::ServerIdentifier(&server_ip)])
+                .append_options([DhcpOption::AddressLeaseTime(LEASE_SECS)])
+                .append_options([DhcpOption::SubnetMask(&subnet_mask)])
+                .append_options([DhcpOption::Router(&[server_ip])])
+                .append_options([DhcpOption::DomainNameServer(&[server_ip])])
+                .encode(&Message::default(), response).ok()?
+        };
+        msg.set_op(OpCode::BootReply);
+        msg.set_xid(request.xid());
+        let _ = msg.set_chaddr(request.chaddr().ok()?);
+        *msg.yiaddr_mut() = Addr(yiaddr);
+        if msg_type != MessageType::NAK {
+            *msg.siaddr_mut() = server_ip;
+        }
+
+        //硬件类型：以太网，地址长度 6
+        response[1] = 1;
+        response[2] = 6;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    //抓包得到的报文，sname 和 file 都是 0，省略后由 packet 补上
+    //头部：op、htype、hlen、hops、xid、secs、flags、ciaddr、yiaddr、siaddr、giaddr、chaddr(16)
+    //Android 手机，MAC 3c:28:6d:1a:2b:4c
+    const DISCOVER:(&str,&str) = (
+        "010106003903f32600000000000000000000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501013d07013c286d1a2b4c390205dc3c0f616e64726f69642d646863702d31330c07506978656c2d37370a0103060f1a1c333a3b2bff",
+    );
+    //选择 192.168.2.10，服务器标识 192.168.2.1
+    const REQUEST:(&str,&str) = (
+        "010106003903f32600000000000000000000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501033d07013c286d1a2b4c3204c0a8020a3604c0a80201390205dc3c0f616e64726f69642d646863702d31330c07506978656c2d37370a0103060f1a1c333a3b2bff",
+    );
+    //从其他网络回来，请求之前的地址 10.0.0.5，没有服务器标识
+    const REQUEST_REBOOT:(&str,&str) = (
+        "01010600a1b2c3d400000000000000000000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501033d07013c286d1a2b4c32040a000005390205dc0c07506978656c2d37370a0103060f1a1c333a3b2bff",
+    );
+    //续租，地址在 ciaddr 中
+    const REQUEST_RENEW:(&str,&str) = (
+        "010106005e6f7a8b00000000c0a8020a0000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501033d07013c286d1a2b4c390205dc0c07506978656c2d37370a0103060f1a1c333a3b2bff",
+    );
+    const RELEASE:(&str,&str) = (
+        "0101060077c1d2e300000000c0a8020a0000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501073604c0a802013d07013c286d1a2b4cff",
+    );
+    //ARP 检查发现地址被占用
+    const DECLINE:(&str,&str) = (
+        "010106003903f32700000000000000000000000000000000000000003c286d1a2b4c00000000000000000000",
+        "3501043204c0a8020a3604c0a802013d07013c286d1a2b4cff",
+    );
+    //笔记本电脑，静态地址 192.168.2.50，MAC a4:c3:f0:11:22:33
+    const INFORM:(&str,&str) = (
+        "0101060012ab34cd00000000c0a80232000000000000000000000000a4c3f011223300000000000000000000",
+        "3501083d0701a4c3f01122330c064c6170746f70370401030f06ff",
+    );
+
+    fn hex(text:&str)->Vec<u8>{
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn packet((head,options):(&str,&str))->Vec<u8>{
+        let mut data = hex(head);
+        data.resize(data.len() + 192, 0);
+        data.extend_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+        data.extend_from_slice(&hex(options));
+        data
+    }
+
+    //换成另一个客户端的 MAC
+    fn with_mac(mut data:Vec<u8>,mac:[u8;6])->Vec<u8>{
+        data[28..34].copy_from_slice(&mac);
+        data
+    }
+
+    const OTHER_MAC:[u8;6] = [0x02, 0x11, 0x22, 0x33, 0x44, 0x55];
+
+    struct Reply{
+        kind:DhcpReply,
+        msg_type:MessageType,
+        yiaddr:[u8;4],
+        server_id:Option<[u8;4]>,
+        xid:u32,
+    }
+
+    fn handle(server:&mut DhcpServer,data:&[u8],now:u64)->Option<Reply>{
+        let mut response = [0u8; DHCP_PACKET_SIZE];
+        let kind = server.handle(&Message::new(data).unwrap(), now, &mut response)?;
+        let msg = Message::new(&response[..]).unwrap();
+        assert_eq!(msg.op().unwrap(), OpCode::BootReply);
+        assert_eq!(msg.chaddr().unwrap()[..6], data[28..34]);
+        let (msg_type,server_id) = v4_options!(msg; MessageType required, ServerIdentifier).unwrap();
+        Some(Reply{ kind, msg_type, yiaddr: msg.yiaddr().0, server_id: server_id.map(|v| v.0), xid: msg.xid() })
+    }
+
+    #[test]
+    fn discover_offers_first_free_address(){
+        let mut server = DhcpServer::new();
+        let reply = handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        assert_eq!(reply.kind, DhcpReply::Broadcast);
+        assert_eq!(reply.msg_type, MessageType::OFFER);
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+        assert_eq!(reply.server_id, Some(SERVER_IP));
+        assert_eq!(reply.xid, 0x3903_f326);
+
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 0).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+    }
+
+    #[test]
+    fn request_acks_offered_address(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        let reply = handle(&mut server, &packet(REQUEST), 1).unwrap();
+        assert_eq!(reply.kind, DhcpReply::Broadcast);
+        assert_eq!(reply.msg_type, MessageType::ACK);
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+
+        let reply = handle(&mut server, &packet(REQUEST_RENEW), 1800).unwrap();
+        assert_eq!(reply.msg_type, MessageType::ACK);
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn same_mac_gets_same_address(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 0).unwrap();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        handle(&mut server, &packet(REQUEST), 0);
+        for now in [10, 100, 1000] {
+            let reply = handle(&mut server, &packet(DISCOVER), now).unwrap();
+            assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+        }
+    }
+
+    #[test]
+    fn nak_on_foreign_address(){
+        let mut server = DhcpServer::new();
+        let reply = handle(&mut server, &packet(REQUEST_REBOOT), 0).unwrap();
+        assert_eq!(reply.msg_type, MessageType::NAK);
+        assert_eq!(reply.yiaddr, [0; 4]);
+
+        //地址已经分配给其他客户端
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        handle(&mut server, &packet(REQUEST), 0).unwrap();
+        let reply = handle(&mut server, &with_mac(packet(REQUEST), OTHER_MAC), 0).unwrap();
+        assert_eq!(reply.msg_type, MessageType::NAK);
+    }
+
+    #[test]
+    fn request_for_other_server_releases_offer(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        let mut request = packet(REQUEST);
+        let pos = request.len() - hex(REQUEST.1).len();
+        let server_id = request[pos..].windows(6).position(|v| v == [0x36, 0x04, 192, 168, 2, 1]).unwrap() + pos;
+        request[server_id + 5] = 254;
+        assert!(handle(&mut server, &request, 0).is_none());
+
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 0).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn release_frees_address(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        handle(&mut server, &packet(REQUEST), 0).unwrap();
+        assert!(handle(&mut server, &packet(RELEASE), 10).is_none());
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 10).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn decline_blocks_address(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        handle(&mut server, &packet(REQUEST), 0).unwrap();
+        assert!(handle(&mut server, &packet(DECLINE), 1).is_none());
+
+        //被占用的地址不再分配，客户端重新获取到下一个地址
+        let reply = handle(&mut server, &packet(DISCOVER), 2).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 2).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 12]);
+        let reply = handle(&mut server, &packet(REQUEST), 2).unwrap();
+        assert_eq!(reply.msg_type, MessageType::NAK);
+    }
+
+    #[test]
+    fn decline_from_other_client_is_ignored(){
+        let mut server = DhcpServer::new();
+        handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), 0).unwrap();
+        let reply = handle(&mut server, &with_mac(packet(REQUEST), OTHER_MAC), 0).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+        //DECLINE 请求的地址是 192.168.2.10，但发送者没有持有它
+        assert!(handle(&mut server, &packet(DECLINE), 1).is_none());
+
+        let reply = handle(&mut server, &packet(DISCOVER), 2).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+        let reply = handle(&mut server, &with_mac(packet(REQUEST), OTHER_MAC), 2).unwrap();
+        assert_eq!(reply.msg_type, MessageType::ACK);
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn inform_acks_without_address(){
+        let mut server = DhcpServer::new();
+        let reply = handle(&mut server, &packet(INFORM), 0).unwrap();
+        assert_eq!(reply.kind, DhcpReply::Unicast([192, 168, 2, 50]));
+        assert_eq!(reply.msg_type, MessageType::ACK);
+        assert_eq!(reply.yiaddr, [0; 4]);
+
+        //没有占用地址池
+        let reply = handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn expired_leases_are_reused(){
+        let mut server = DhcpServer::new();
+        //没有确认的 OFFER 只保留 OFFER_SECS
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), OFFER_SECS - 1).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), [2, 0, 0, 0, 0, 1]), OFFER_SECS).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+
+        let mut server = DhcpServer::new();
+        handle(&mut server, &packet(DISCOVER), 0).unwrap();
+        handle(&mut server, &packet(REQUEST), 0).unwrap();
+        let reply = handle(&mut server, &with_mac(packet(DISCOVER), OTHER_MAC), LEASE_SECS as u64 - 1).unwrap();
+        assert_eq!(reply.yiaddr, [192, 168, 2, 11]);
+        let reply = handle(&mut server, &with_mac(packet(REQUEST), [2, 0, 0, 0, 0, 1]), LEASE_SECS as u64).unwrap();
+        assert_eq!(reply.msg_type, MessageType::ACK);
+        assert_eq!(reply.yiaddr, [192, 168, 2, 10]);
+    }
+
+    #[test]
+    fn pool_exhausted(){
+        let mut server = DhcpServer::new();
+        for i in 0..POOL_SIZE as u8 {
+            let reply = handle(&mut server, &with_mac(packet(DISCOVER), [2, 0, 0, 0, 0, i]), 0).unwrap();
+            assert_eq!(reply.yiaddr, [192, 168, 2, 10 + i]);
+        }
+        assert!(handle(&mut server, &packet(DISCOVER), 0).is_none());
+    }
+}
@@ -0,0 +1,759 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec as AllocVec;
+use core::future::Future;
+use core::pin::Pin;
+use heapless::Vec;
+
+//网页服务的请求解析、路由和响应，不涉及 socket，可以用固定的请求数据调用
+pub const HEADER_MAX:usize = 32;
+pub const CONTENT_TYPE_HTML:&str = "text/html; charset=utf-8";
+pub const CONTENT_TYPE_JSON:&str = "application/json";
+pub const CONTENT_TYPE_TEXT:&str = "text/plain; charset=utf-8";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Status{
+    Ok,
+    Found,
+    BadRequest,
+    NotFound,
+    MethodNotAllowed,
+    PayloadTooLarge,
+    InternalServerError,
+}
+
+impl Status {
+    pub fn code(&self)->u16{
+        match self {
+            Status::Ok => 200,
+            Status::Found => 302,
+            Status::BadRequest => 400,
+            Status::NotFound => 404,
+            Status::MethodNotAllowed => 405,
+            Status::PayloadTooLarge => 413,
+            Status::InternalServerError => 500,
+        }
+    }
+
+    pub fn reason(&self)->&'static str{
+        match self {
+            Status::Ok => "OK",
+            Status::Found => "Found",
+            Status::BadRequest => "Bad Request",
+            Status::NotFound => "Not Found",
+            Status::MethodNotAllowed => "Method Not Allowed",
+            Status::PayloadTooLarge => "Payload Too Large",
+            Status::InternalServerError => "Internal Server Error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParseError{
+    Partial,//请求头还没有读完
+    Invalid,
+}
+
+pub struct Request<'a>{
+    pub method:&'a str,
+    pub path:&'a str,//不包含查询字符串
+    pub query:&'a str,//? 之后的部分，没有时为空
+    headers:&'a [httparse::Header<'a>],
+    head_len:usize,
+    pub body:&'a [u8],//已读取的请求体
+}
+
+impl<'a> Request<'a> {
+    //解析请求头，data 中请求头之后的数据作为请求体
+    pub fn parse(data:&'a [u8],headers:&'a mut [httparse::Header<'a>])->Result<Self,ParseError>{
+        let mut request = httparse::Request::new(headers);
+        let head_len = match request.parse(data) {
+            Ok(httparse::Status::Complete(len)) => len,
+            Ok(httparse::Status::Partial) => return Err(ParseError::Partial),
+            Err(_) => return Err(ParseError::Invalid),
+        };
+        let httparse::Request{ method, path, headers, .. } = request;
+        let target = path.ok_or(ParseError::Invalid)?;
+        let (path,query) = target.split_once('?').unwrap_or((target, ""));
+        let mut request = Self{
+            method: method.ok_or(ParseError::Invalid)?,
+            path,
+            query,
+            headers,
+            head_len,
+            body: &data[head_len..],
+        };
+        if let Some(len) = request.content_length() {
+            request.body = &request.body[..len.min(request.body.len())];
+        }
+        Ok(request)
+    }
+
+    //请求头的名称不区分大小写
+    pub fn header(&self,name:&str)->Option<&'a str>{
+        self.headers.iter()
+            .find(|v| v.name.eq_ignore_ascii_case(name))
+            .and_then(|v| core::str::from_utf8(v.value).ok())
+            .map(|v| v.trim())
+    }
+
+    pub fn content_length(&self)->Option<usize>{
+        self.header("Content-Length").and_then(|v| v.parse().ok())
+    }
+
+    //请求头和 Content-Length 指定的请求体的总长度
+    pub fn total_len(&self)->usize{
+        self.head_len + self.content_length().unwrap_or(0)
+    }
+
+    //请求体是否已读完，读完后 body 只包含 Content-Length 指定的部分
+    pub fn body_complete(&self)->bool{
+        self.body.len() >= self.content_length().unwrap_or(0)
+    }
+
+    //查询参数，按 urlencoded 表单解码，转义不对时返回 Encoding
+    pub fn query_params(&self)->Result<Form,FormError>{
+        Form::from_urlencoded(self.query)
+    }
+
+    //解码后的参数值，没有或解码失败时返回 None
+    pub fn query_param(&self,name:&str)->Option<String>{
+        self.query_params().ok()?.get(name).map(String::from)
+    }
+}
+
+pub enum Body{
+    Empty,
+    Static(&'static [u8]),
+    Owned(AllocVec<u8>),
+}
+
+impl Body {
+    pub fn as_bytes(&self)->&[u8]{
+        match self {
+            Body::Empty => &[],
+            Body::Static(v) => v,
+            Body::Owned(v) => v.as_slice(),
+        }
+    }
+}
+
+///响应，handler 用构建方法设置状态、类型、头和内容，写入时自动加上 Content-Length
+pub struct Response{
+    status:Status,
+    content_type:Option<&'static str>,
+    headers:Vec<(&'static str,String),4>,
+    body:Body,
+    after:Option<fn()>,//响应发送完成后执行，例如重启
+}
+
+impl Response {
+    pub fn new(status:Status)->Self{
+        Self{ status, content_type: None, headers: Vec::new(), body: Body::Empty, after: None }
+    }
+
+    pub fn ok()->Self{
+        Self::new(Status::Ok)
+    }
+
+    pub fn html(content:&'static str)->Self{
+        Self::ok().content_type(CONTENT_TYPE_HTML).body(Body::Static(content.as_bytes()))
+    }
+
+    pub fn json(content:String)->Self{
+        Self::ok().content_type(CONTENT_TYPE_JSON).body(Body::Owned(content.into_bytes()))
+    }
+
+    pub fn redirect(location:&str)->Self{
+        Self::new(Status::Found).header("Location", location)
+    }
+
+    //错误状态，内容为状态说明
+    pub fn error(status:Status)->Self{
+        Self::new(status).content_type(CONTENT_TYPE_TEXT).body(Body::Owned(format!("{} {}", status.code(), status.reason()).into_bytes()))
+    }
+
+    pub fn content_type(mut self,content_type:&'static str)->Self{
+        self.content_type = Some(content_type);
+        self
+    }
+
+    //最多 4 个，超出的忽略
+    pub fn header(mut self,name:&'static str,value:&str)->Self{
+        let _ = self.headers.push((name, String::from(value)));
+        self
+    }
+
+    pub fn body(mut self,body:Body)->Self{
+        self.body = body;
+        self
+    }
+
+    pub fn then(mut self,after:fn())->Self{
+        self.after = Some(after);
+        self
+    }
+
+    pub fn status(&self)->Status{
+        self.status
+    }
+
+    pub fn body_bytes(&self)->&[u8]{
+        self.body.as_bytes()
+    }
+
+    pub fn after(&self)->Option<fn()>{
+        self.after
+    }
+
+    //状态行和响应头，每个连接只处理一个请求
+    pub fn head(&self)->String{
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status.code(), self.status.reason());
+        if let Some(content_type) = self.content_type {
+            head.push_str(&format!("Content-Type: {}\r\n", content_type));
+        }
+        for (name,value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", self.body.as_bytes().len()));
+        head
+    }
+}
+
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Response> + 'a>>;
+pub type Handler = for<'a> fn(&'a Request<'a>) -> HandlerFuture<'a>;
+
+pub struct Route{
+    method:&'static str,
+    path:&'static str,//以 * 结尾时匹配前缀
+    handler:Handler,
+}
+
+impl Route {
+    pub const fn new(method:&'static str,path:&'static str,handler:Handler)->Self{
+        Self{ method, path, handler }
+    }
+
+    fn matches(&self,path:&str)->bool{
+        match self.path.strip_suffix('*') {
+            Some(prefix) => path.starts_with(prefix),
+            None => self.path == path,
+        }
+    }
+}
+
+//按顺序查找第一个匹配的路由，路径匹配但方法不对时返回 405，并在 Allow 中列出支持的方法
+pub async fn dispatch(routes:&[Route],request:&Request<'_>)->Response{
+    let mut allow:Vec<&str,4> = Vec::new();
+    for route in routes.iter().filter(|v| v.matches(request.path)) {
+        if route.method == request.method {
+            return (route.handler)(request).await;
+        }
+        if !allow.contains(&route.method) {
+            let _ = allow.push(route.method);
+        }
+    }
+    if allow.is_empty() {
+        Response::error(Status::NotFound)
+    } else {
+        let allow = allow.join(", ");
+        Response::error(Status::MethodNotAllowed).header("Allow", &allow)
+    }
+}
+
+pub const FORM_FIELD_MAX:usize = 20;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FormError{
+    UnsupportedType,//不支持的 Content-Type
+    Encoding,//不是 UTF-8 或转义不对
+    Malformed,
+    TooManyFields,
+}
+
+///表单字段，支持 application/x-www-form-urlencoded、multipart/form-data 和只有一层的 JSON 对象
+///JSON 中的数字和布尔值按原文保存，null 的字段忽略
+#[derive(Debug, Default)]
+pub struct Form{
+    fields:AllocVec<(String,String)>,
+}
+
+impl Form {
+    pub fn parse(request:&Request)->Result<Self,FormError>{
+        let content_type = request.header("Content-Type").unwrap_or("");
+        let (mime,params) = content_type.split_once(';').unwrap_or((content_type, ""));
+        let mime = mime.trim();
+        if mime.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+            Self::from_urlencoded(core::str::from_utf8(request.body).map_err(|_| FormError::Encoding)?)
+        } else if mime.eq_ignore_ascii_case("multipart/form-data") {
+            let boundary = params.split(';')
+                .find_map(|v| v.trim().strip_prefix("boundary="))
+                .map(|v| v.trim_matches('"'))
+                .filter(|v| !v.is_empty())
+                .ok_or(FormError::Malformed)?;
+            Self::from_multipart(request.body, boundary)
+        } else if mime.eq_ignore_ascii_case("application/json") {
+            Self::from_json(core::str::from_utf8(request.body).map_err(|_| FormError::Encoding)?)
+        } else {
+            Err(FormError::UnsupportedType)
+        }
+    }
+
+    pub fn get(&self,name:&str)->Option<&str>{
+        self.iter().find(|v| v.0 == name).map(|v| v.1)
+    }
+
+    pub fn iter(&self)->impl Iterator<Item=(&str,&str)>{
+        self.fields.iter().map(|(name,value)| (name.as_str(), value.as_str()))
+    }
+
+    fn push(&mut self,name:String,value:String)->Result<(),FormError>{
+        if self.fields.len() >= FORM_FIELD_MAX {
+            return Err(FormError::TooManyFields);
+        }
+        self.fields.push((name, value));
+        Ok(())
+    }
+
+    //name=value&name=value，+ 表示空格
+    pub fn from_urlencoded(body:&str)->Result<Self,FormError>{
+        let mut form = Self::default();
+        for pair in body.split('&').filter(|v| !v.is_empty()) {
+            let (name,value) = pair.split_once('=').unwrap_or((pair, ""));
+            let name = percent_decode(name, true).ok_or(FormError::Encoding)?;
+            let value = percent_decode(value, true).ok_or(FormError::Encoding)?;
+            form.push(name, value)?;
+        }
+        Ok(form)
+    }
+
+    //各部分以 --boundary 开头，部分的头和内容之间是空行，最后以 --boundary-- 结束
+    pub fn from_multipart(body:&[u8],boundary:&str)->Result<Self,FormError>{
+        let mut delimiter = AllocVec::from(&b"\r\n--"[..]);
+        delimiter.extend_from_slice(boundary.as_bytes());
+        let mut form = Self::default();
+        //第一个分隔符前面可以没有换行
+        let mut pos = find(body, &delimiter[2..], 0).ok_or(FormError::Malformed)? + delimiter.len() - 2;
+        loop {
+            let rest = &body[pos..];
+            if rest.starts_with(b"--") {
+                return Ok(form);
+            }
+            let rest = rest.strip_prefix(b"\r\n").ok_or(FormError::Malformed)?;
+            let head_start = body.len() - rest.len();
+            let head_end = find(body, b"\r\n\r\n", head_start).ok_or(FormError::Malformed)?;
+            let head = core::str::from_utf8(&body[head_start..head_end]).map_err(|_| FormError::Encoding)?;
+            let content_end = find(body, &delimiter, head_end + 4).ok_or(FormError::Malformed)?;
+
+            let name = head.split("\r\n")
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name,_)| name.trim().eq_ignore_ascii_case("Content-Disposition"))
+                .and_then(|(_,value)| disposition_name(value))
+                .ok_or(FormError::Malformed)?;
+            let value = core::str::from_utf8(&body[head_end + 4..content_end]).map_err(|_| FormError::Encoding)?;
+            form.push(String::from(name), String::from(value))?;
+            pos = content_end + delimiter.len();
+        }
+    }
+
+    //{"name":"value","count":1}，不支持嵌套
+    pub fn from_json(body:&str)->Result<Self,FormError>{
+        let mut parser = JsonParser{ text: body.as_bytes(), pos: 0 };
+        let mut form = Self::default();
+        parser.expect(b'{')?;
+        if parser.peek() == Some(b'}') {
+            parser.pos += 1;
+        } else {
+            loop {
+                let name = parser.string()?;
+                parser.expect(b':')?;
+                //null 的字段忽略
+                if let Some(value) = parser.value()? {
+                    form.push(name, value)?;
+                }
+                match parser.next_byte() {
+                    Some(b',') => continue,
+                    Some(b'}') => break,
+                    _ => return Err(FormError::Malformed),
+                }
+            }
+        }
+        if parser.peek().is_some() {
+            return Err(FormError::Malformed);
+        }
+        Ok(form)
+    }
+}
+
+//Content-Disposition: form-data; name="ssid"; filename="a.txt" 中的 name
+fn disposition_name(value:&str)->Option<&str>{
+    value.split(';')
+        .skip(1)
+        .find_map(|v| v.trim().strip_prefix("name="))
+        .map(|v| v.trim_matches('"'))
+}
+
+fn find(data:&[u8],pattern:&[u8],start:usize)->Option<usize>{
+    data.get(start..)?
+        .windows(pattern.len())
+        .position(|v| v == pattern)
+        .map(|v| v + start)
+}
+
+//解码 %XX，plus_as_space 时 + 解码为空格，结果必须是 UTF-8
+pub fn percent_decode(text:&str,plus_as_space:bool)->Option<String>{
+    let bytes = text.as_bytes();
+    let mut result = AllocVec::with_capacity(bytes.len());
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'%' => {
+                let hex = bytes.get(pos + 1..pos + 3)?;
+                if !hex.iter().all(|v| v.is_ascii_hexdigit()) {
+                    return None;
+                }
+                result.push(u8::from_str_radix(core::str::from_utf8(hex).ok()?, 16).ok()?);
+                pos += 3;
+            }
+            b'+' if plus_as_space => {
+                result.push(b' ');
+                pos += 1;
+            }
+            byte => {
+                result.push(byte);
+                pos += 1;
+            }
+        }
+    }
+    String::from_utf8(result).ok()
+}
+
+struct JsonParser<'a>{
+    text:&'a [u8],
+    pos:usize,
+}
+
+impl<'a> JsonParser<'a> {
+    //下一个非空白字符，不移动位置
+    fn peek(&mut self)->Option<u8>{
+        while let Some(byte) = self.text.get(self.pos) {
+            if !matches!(byte, b' ' | b'\t' | b'\r' | b'\n') {
+                return Some(*byte);
+            }
+            self.pos += 1;
+        }
+        None
+    }
+
+    fn next_byte(&mut self)->Option<u8>{
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn expect(&mut self,byte:u8)->Result<(),FormError>{
+        if self.next_byte() == Some(byte) { Ok(()) } else { Err(FormError::Malformed) }
+    }
+
+    fn hex4(&mut self)->Result<u32,FormError>{
+        let hex = self.text.get(self.pos..self.pos + 4).ok_or(FormError::Malformed)?;
+        let hex = core::str::from_utf8(hex).map_err(|_| FormError::Encoding)?;
+        if !hex.bytes().all(|v| v.is_ascii_hexdigit()) {
+            return Err(FormError::Encoding);
+        }
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).map_err(|_| FormError::Encoding)
+    }
+
+    fn string(&mut self)->Result<String,FormError>{
+        self.expect(b'"')?;
+        let mut result = AllocVec::new();
+        loop {
+            let byte = *self.text.get(self.pos).ok_or(FormError::Malformed)?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escape = *self.text.get(self.pos).ok_or(FormError::Malformed)?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.hex4()?;
+                            //UTF-16 代理对
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.text.get(self.pos..self.pos + 2) != Some(&b"\\u"[..]) {
+                                    return Err(FormError::Encoding);
+                                }
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(FormError::Encoding);
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            char::from_u32(code).ok_or(FormError::Encoding)?
+                        }
+                        _ => return Err(FormError::Encoding),
+                    };
+                    let mut buf = [0u8; 4];
+                    result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                byte if byte < 0x20 => return Err(FormError::Malformed),
+                byte => result.push(byte),
+            }
+        }
+        String::from_utf8(result).map_err(|_| FormError::Encoding)
+    }
+
+    //字符串、数字和布尔值，null 返回 None
+    fn value(&mut self)->Result<Option<String>,FormError>{
+        match self.peek() {
+            Some(b'"') => Ok(Some(self.string()?)),
+            Some(b'{') | Some(b'[') | None => Err(FormError::Malformed),
+            Some(_) => {
+                let start = self.pos;
+                while let Some(byte) = self.text.get(self.pos) {
+                    if matches!(byte, b',' | b'}' | b' ' | b'\t' | b'\r' | b'\n') {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                let token = core::str::from_utf8(&self.text[start..self.pos]).map_err(|_| FormError::Encoding)?;
+                match token {
+                    "null" => Ok(None),
+                    "true" | "false" => Ok(Some(String::from(token))),
+                    _ if token.parse::<f64>().is_ok() => Ok(Some(String::from(token))),
+                    _ => Err(FormError::Malformed),
+                }
+            }
+        }
+    }
+}
+
+//转成带引号的 json 字符串，转义引号、反斜杠和控制字符
+pub fn json_string(text:&str)->String{
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::task::{Context, Poll, Waker};
+
+    //handler 都是立即完成的，轮询一次即可
+    fn block_on<F:Future>(future:F)->F::Output{
+        let mut future = core::pin::pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future not ready"),
+        }
+    }
+
+    fn with_request<T>(data:&[u8],f:impl FnOnce(Result<Request,ParseError>)->T)->T{
+        let mut headers = [httparse::EMPTY_HEADER; HEADER_MAX];
+        f(Request::parse(data, &mut headers))
+    }
+
+    fn page<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+        Box::pin(async move { Response::html("page") })
+    }
+
+    fn echo<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+        Box::pin(async move { Response::json(String::from(request.path)) })
+    }
+
+    static ROUTES:[Route;3] = [
+        Route::new("GET", "/config", page),
+        Route::new("POST", "/config", echo),
+        Route::new("GET", "/static/*", echo),
+    ];
+
+    #[test]
+    fn parse_request_line_and_headers(){
+        with_request(b"GET /scan?refresh=1 HTTP/1.1\r\nHost: work-timer.local\r\ncontent-type: text/plain\r\n\r\n", |request| {
+            let request = request.unwrap();
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.path, "/scan");
+            assert_eq!(request.query, "refresh=1");
+            assert_eq!(request.header("Content-Type"), Some("text/plain"));
+            assert_eq!(request.header("HOST"), Some("work-timer.local"));
+            assert_eq!(request.header("Accept"), None);
+            assert!(request.body.is_empty());
+            assert!(request.body_complete());
+        });
+    }
+
+    #[test]
+    fn parse_partial_request(){
+        with_request(b"POST /configure_wifi HTTP/1.1\r\nContent-Len", |request| {
+            assert_eq!(request.err(), Some(ParseError::Partial));
+        });
+        with_request(b"", |request| {
+            assert_eq!(request.err(), Some(ParseError::Partial));
+        });
+    }
+
+    #[test]
+    fn parse_invalid_request(){
+        with_request(b"GET /config HTTP/9\r\n\r\n", |request| {
+            assert_eq!(request.err(), Some(ParseError::Invalid));
+        });
+        with_request(b"\x01\x02 / HTTP/1.1\r\n\r\n", |request| {
+            assert_eq!(request.err(), Some(ParseError::Invalid));
+        });
+    }
+
+    #[test]
+    fn body_truncated_to_content_length(){
+        let data = b"POST /config HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello world";
+        with_request(data, |request| {
+            let request = request.unwrap();
+            assert_eq!(request.content_length(), Some(5));
+            assert_eq!(request.body, b"hello");
+            assert!(request.body_complete());
+            assert_eq!(request.total_len(), data.len() - 6);
+        });
+    }
+
+    #[test]
+    fn body_incomplete_until_content_length(){
+        let data = b"POST /config HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello";
+        with_request(data, |request| {
+            let request = request.unwrap();
+            assert_eq!(request.body, b"hello");
+            assert!(!request.body_complete());
+            assert_eq!(request.total_len(), data.len() + 5);
+        });
+    }
+
+    #[test]
+    fn query_split(){
+        with_request(b"GET /scan?a=1&&b=&c&ssid=My%20Wifi+5G HTTP/1.1\r\n\r\n", |request| {
+            let request = request.unwrap();
+            assert_eq!(request.path, "/scan");
+            assert_eq!(request.query, "a=1&&b=&c&ssid=My%20Wifi+5G");
+            let params = request.query_params().unwrap();
+            let params:AllocVec<_> = params.iter().collect();
+            assert_eq!(params, [("a", "1"), ("b", ""), ("c", ""), ("ssid", "My Wifi 5G")]);
+            assert_eq!(request.query_param("ssid").as_deref(), Some("My Wifi 5G"));
+            assert_eq!(request.query_param("d"), None);
+        });
+        with_request(b"GET /scan HTTP/1.1\r\n\r\n", |request| {
+            let request = request.unwrap();
+            assert_eq!(request.query, "");
+            assert_eq!(request.query_params().unwrap().iter().count(), 0);
+        });
+        with_request(b"GET /scan?a=%zz HTTP/1.1\r\n\r\n", |request| {
+            let request = request.unwrap();
+            assert_eq!(request.query_params().err(), Some(FormError::Encoding));
+            assert_eq!(request.query_param("a"), None);
+        });
+    }
+
+    #[test]
+    fn dispatch_matches_method_and_path(){
+        with_request(b"POST /config HTTP/1.1\r\n\r\n", |request| {
+            let response = block_on(dispatch(&ROUTES, &request.unwrap()));
+            assert_eq!(response.status(), Status::Ok);
+            assert_eq!(response.body_bytes(), b"/config");
+        });
+        with_request(b"GET /static/app.js HTTP/1.1\r\n\r\n", |request| {
+            let response = block_on(dispatch(&ROUTES, &request.unwrap()));
+            assert_eq!(response.body_bytes(), b"/static/app.js");
+        });
+    }
+
+    #[test]
+    fn dispatch_not_found(){
+        with_request(b"GET /missing HTTP/1.1\r\n\r\n", |request| {
+            let response = block_on(dispatch(&ROUTES, &request.unwrap()));
+            assert_eq!(response.status(), Status::NotFound);
+            assert!(response.head().starts_with("HTTP/1.1 404 Not Found\r\n"));
+        });
+    }
+
+    #[test]
+    fn dispatch_method_not_allowed(){
+        with_request(b"DELETE /config HTTP/1.1\r\n\r\n", |request| {
+            let response = block_on(dispatch(&ROUTES, &request.unwrap()));
+            assert_eq!(response.status(), Status::MethodNotAllowed);
+            assert!(response.head().contains("\r\nAllow: GET, POST\r\n"));
+        });
+    }
+
+    #[test]
+    fn response_head(){
+        let head = Response::html("<p>设置</p>").head();
+        assert_eq!(head, "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 13\r\nConnection: close\r\n\r\n");
+
+        let head = Response::redirect("http://192.168.2.1/config").head();
+        assert!(head.starts_with("HTTP/1.1 302 Found\r\n"));
+        assert!(head.contains("\r\nLocation: http://192.168.2.1/config\r\n"));
+        assert!(head.contains("\r\nContent-Length: 0\r\n"));
+    }
+
+    #[test]
+    fn form_urlencoded(){
+        let form = Form::from_urlencoded("ssid=My+Wifi&password=a%26b%3Dc&name=%E8%AE%BE%E5%A4%87").unwrap();
+        assert_eq!(form.get("ssid"), Some("My Wifi"));
+        assert_eq!(form.get("password"), Some("a&b=c"));
+        assert_eq!(form.get("name"), Some("设备"));
+        assert_eq!(Form::from_urlencoded("a=%4").err(), Some(FormError::Encoding));
+    }
+
+    #[test]
+    fn form_multipart(){
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"ssid\"\r\n\r\nhome\r\n--XyZ\r\ncontent-disposition: form-data; name=\"password\"\r\n\r\n pass word \r\n--XyZ--\r\n";
+        let form = Form::from_multipart(body, "XyZ").unwrap();
+        assert_eq!(form.get("ssid"), Some("home"));
+        assert_eq!(form.get("password"), Some(" pass word "));
+        assert_eq!(Form::from_multipart(b"--XyZ\r\n\r\nvalue", "XyZ").err(), Some(FormError::Malformed));
+    }
+
+    #[test]
+    fn form_json(){
+        let form = Form::from_json(r#"{"ssid":"a\"bé😀","cycles":4,"enabled":true,"skip":null}"#).unwrap();
+        assert_eq!(form.get("ssid"), Some("a\"bé😀"));
+        assert_eq!(form.get("cycles"), Some("4"));
+        assert_eq!(form.get("enabled"), Some("true"));
+        assert_eq!(form.get("skip"), None);
+        assert_eq!(Form::from_json(r#"{"a":{"b":1}}"#).err(), Some(FormError::Malformed));
+        assert_eq!(Form::from_json("{}").unwrap().iter().count(), 0);
+    }
+
+    #[test]
+    fn form_from_request_content_type(){
+        with_request(b"POST /config HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 9\r\n\r\n{\"a\":\"b\"}", |request| {
+            assert_eq!(Form::parse(&request.unwrap()).unwrap().get("a"), Some("b"));
+        });
+        with_request(b"POST /config HTTP/1.1\r\nContent-Type: text/plain\r\n\r\n", |request| {
+            assert_eq!(Form::parse(&request.unwrap()).err(), Some(FormError::UnsupportedType));
+        });
+    }
+
+    #[test]
+    fn json_string_escapes(){
+        assert_eq!(json_string("工作"), "\"工作\"");
+        assert_eq!(json_string(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(json_string("a\nb\u{1}"), r#""a\u000ab\u0001""#);
+    }
+}
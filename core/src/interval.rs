@@ -0,0 +1,241 @@
+use core::str::FromStr;
+use heapless::{String, Vec};
+
+pub const INTERVAL_BLOCK_STEPS:usize = 4;
+pub const INTERVAL_BLOCKS:usize = 6;
+//单步最长时间，页面按 分:秒 显示剩余时间
+pub const INTERVAL_STEP_SECS_MAX:u32 = 99 * 60 + 59;
+
+#[derive(Debug,Default,Clone)]
+pub struct IntervalStep{
+    pub name:String<16>,
+    pub secs:u32,
+}
+
+//一组步骤，按 repeat 重复执行，repeat 为 1 时是单独的步骤，例如热身
+#[derive(Debug,Default,Clone)]
+pub struct IntervalBlock{
+    pub repeat:u8,
+    pub steps:Vec<IntervalStep,INTERVAL_BLOCK_STEPS>,
+}
+
+#[derive(Debug,Default,Clone)]
+pub struct IntervalSequence{
+    pub name:String<20>,
+    pub blocks:Vec<IntervalBlock,INTERVAL_BLOCKS>,
+}
+
+//执行到的位置
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub struct IntervalCursor{
+    pub block:usize,
+    pub round:u8,//从 1 开始
+    pub step:usize,
+}
+
+//解析时长，支持 40s、5m、1m30s、1h，纯数字按秒
+fn parse_secs(text:&str)->Option<u32>{
+    let text = text.trim();
+    if let Ok(v) = text.parse::<u32>() {
+        return Some(v);
+    }
+    let mut secs = 0u32;
+    let mut number = 0u32;
+    let mut has_number = false;
+    for c in text.chars() {
+        if let Some(d) = c.to_digit(10) {
+            number = number.checked_mul(10)?.checked_add(d)?;
+            has_number = true;
+            continue;
+        }
+        if !has_number {
+            return None;
+        }
+        let unit = match c {
+            'h' | 'H' => 3600,
+            'm' | 'M' => 60,
+            's' | 'S' => 1,
+            _ => return None,
+        };
+        secs = secs.checked_add(number.checked_mul(unit)?)?;
+        number = 0;
+        has_number = false;
+    }
+    if has_number {
+        return None;
+    }
+    Some(secs)
+}
+
+//解析步骤，例如 "工作 40s"
+fn parse_step(text:&str)->Option<IntervalStep>{
+    let (name,secs) = text.trim().rsplit_once(' ')?;
+    let secs = parse_secs(secs)?;
+    if secs == 0 || secs > INTERVAL_STEP_SECS_MAX {
+        return None;
+    }
+    Some(IntervalStep{
+        name: String::from_str(name.trim()).ok()?,
+        secs,
+    })
+}
+
+//解析一组，例如 "8x(工作 40s, 休息 20s)" 或 "热身 5m"
+fn parse_block(text:&str)->Option<IntervalBlock>{
+    let text = text.trim();
+    let mut block = IntervalBlock{ repeat: 1, steps: Vec::new() };
+    if let Some((repeat,rest)) = text.split_once(['x', 'X', '×']) {
+        //括号也可以用全角
+        let inner = rest.trim().strip_prefix(['(', '（']).and_then(|v| v.strip_suffix([')', '）']));
+        if let (Ok(repeat),Some(inner)) = (repeat.trim().parse::<u8>(),inner) {
+            if repeat == 0 {
+                return None;
+            }
+            block.repeat = repeat;
+            for step in inner.split([',', '，']) {
+                block.steps.push(parse_step(step)?).ok()?;
+            }
+            return Some(block);
+        }
+    }
+    block.steps.push(parse_step(text)?).ok()?;
+    Some(block)
+}
+
+impl IntervalSequence {
+
+    //解析一行配置，例如 "间歇跑：热身 5m; 8x(工作 40s, 休息 20s); 放松 3m"
+    pub fn parse(line:&str)->Option<Self>{
+        let (name,rest) = line.trim().split_once([':', '：'])?;
+        let mut sequence = Self{
+            name: String::from_str(name.trim()).ok()?,
+            blocks: Vec::new(),
+        };
+        for block in rest.split([';', '；']) {
+            if block.trim().is_empty() {
+                continue;
+            }
+            sequence.blocks.push(parse_block(block)?).ok()?;
+        }
+        if sequence.blocks.is_empty() {
+            return None;
+        }
+        Some(sequence)
+    }
+
+    pub fn is_valid(&self)->bool{
+        self.name.len() <= self.name.capacity()
+            && core::str::from_utf8(self.name.as_bytes()).is_ok()
+            && self.blocks.len() <= INTERVAL_BLOCKS
+            && self.blocks.iter().all(|block| {
+                block.repeat > 0 && !block.steps.is_empty() && block.steps.len() <= INTERVAL_BLOCK_STEPS
+                    && block.steps.iter().all(|step| step.secs > 0 && step.secs <= INTERVAL_STEP_SECS_MAX
+                        && step.name.len() <= step.name.capacity()
+                        && core::str::from_utf8(step.name.as_bytes()).is_ok())
+            })
+    }
+
+    pub fn first(&self)->Option<IntervalCursor>{
+        if self.blocks.is_empty() {
+            return None;
+        }
+        Some(IntervalCursor{ block: 0, round: 1, step: 0 })
+    }
+
+    pub fn next(&self,cursor:IntervalCursor)->Option<IntervalCursor>{
+        let block = self.blocks.get(cursor.block)?;
+        if cursor.step + 1 < block.steps.len() {
+            return Some(IntervalCursor{ step: cursor.step + 1, ..cursor });
+        }
+        if cursor.round < block.repeat {
+            return Some(IntervalCursor{ round: cursor.round + 1, step: 0, ..cursor });
+        }
+        if cursor.block + 1 < self.blocks.len() {
+            return Some(IntervalCursor{ block: cursor.block + 1, round: 1, step: 0 });
+        }
+        None
+    }
+
+    pub fn step(&self,cursor:IntervalCursor)->Option<&IntervalStep>{
+        self.blocks.get(cursor.block)?.steps.get(cursor.step)
+    }
+
+    pub fn repeat(&self,cursor:IntervalCursor)->u8{
+        self.blocks.get(cursor.block).map_or(1,|v| v.repeat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(sequence:&IntervalSequence)->alloc::vec::Vec<(&str,u32,u8)>{
+        let mut steps = alloc::vec::Vec::new();
+        let mut cursor = sequence.first();
+        while let Some(v) = cursor {
+            let step = sequence.step(v).unwrap();
+            steps.push((step.name.as_str(),step.secs,v.round));
+            cursor = sequence.next(v);
+        }
+        steps
+    }
+
+    #[test]
+    fn secs(){
+        assert_eq!(parse_secs("40"), Some(40));
+        assert_eq!(parse_secs("40s"), Some(40));
+        assert_eq!(parse_secs("1m30s"), Some(90));
+        assert_eq!(parse_secs("1H"), Some(3600));
+        assert_eq!(parse_secs("m"), None);
+        assert_eq!(parse_secs("5d"), None);
+        assert_eq!(parse_secs("1m30"), None);
+        //溢出
+        assert_eq!(parse_secs("2000000h"), None);
+        assert_eq!(parse_secs("99999999999s"), None);
+    }
+
+    #[test]
+    fn repeated_block(){
+        let block = parse_block("8x(工作 40s, 休息 20s)").unwrap();
+        assert_eq!(block.repeat, 8);
+        assert_eq!(block.steps.len(), 2);
+        assert_eq!(block.steps[0].name.as_str(), "工作");
+        assert_eq!(block.steps[0].secs, 40);
+        assert_eq!(block.steps[1].name.as_str(), "休息");
+        assert_eq!(block.steps[1].secs, 20);
+
+        let block = parse_block("热身 5m").unwrap();
+        assert_eq!(block.repeat, 1);
+        assert_eq!(block.steps[0].secs, 300);
+
+        assert!(parse_block("0x(工作 40s)").is_none());
+        assert!(parse_block("8x(工作 0s)").is_none());
+        assert!(parse_block("8x(工作 40s, 休息 1000000h)").is_none());
+        assert!(parse_block("工作 100m").is_none());
+    }
+
+    #[test]
+    fn full_width_separators(){
+        let sequence = IntervalSequence::parse("间歇跑：热身 5m；2×（工作 40s，休息 20s）；放松 3m").unwrap();
+        assert_eq!(sequence.name.as_str(), "间歇跑");
+        assert_eq!(names(&sequence), [("热身",300,1),("工作",40,1),("休息",20,1),("工作",40,2),("休息",20,2),("放松",180,1)]);
+    }
+
+    #[test]
+    fn names_containing_x(){
+        let sequence = IntervalSequence::parse("Box: relax 1m; 2x(box jumps 30s, rest 10s); Xmas 1s").unwrap();
+        assert_eq!(sequence.name.as_str(), "Box");
+        assert_eq!(names(&sequence), [("relax",60,1),("box jumps",30,1),("rest",10,1),("box jumps",30,2),("rest",10,2),("Xmas",1,1)]);
+    }
+
+    #[test]
+    fn invalid_lines(){
+        assert!(IntervalSequence::parse("热身 5m").is_none());
+        assert!(IntervalSequence::parse("间歇跑：").is_none());
+        assert!(IntervalSequence::parse("间歇跑：热身 5m; 0x(工作 40s)").is_none());
+        assert!(IntervalSequence::parse("间歇跑：热身 1000000h").is_none());
+        //超过组数上限
+        assert!(IntervalSequence::parse("a: a 1s; b 1s; c 1s; d 1s; e 1s; f 1s; g 1s").is_none());
+        assert!(IntervalSequence::parse("a: a 1s; ; b 1s").is_some_and(|v| v.is_valid()));
+    }
+}
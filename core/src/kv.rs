@@ -0,0 +1,736 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+use embedded_storage::nor_flash::NorFlash;
+use crate::record::crc32;
+
+//日志结构的键值存储
+//存储区分为两半，同一时间只使用其中一半，修改时把新值追加到末尾，读取时以最后一条为准
+//追加只写入擦除过的空白位置，不擦除扇区，写入中途断电只影响正在写的这一条，打开时校验不通过就忽略
+//写满后把每个键的最新值整理到另一半：先擦除，再写入条目，最后写入头部才切换，中途断电仍使用原来的一半
+//每一半开头是头部：magic、序号、前 8 字节的 crc，之后是依次追加的条目
+//条目：类型(u8)、键长度(u8)、值长度(u16)、crc(u32)、键、值，按 4 字节对齐，类型为 0xFF 表示后面是空白
+//只依赖 embedded_storage::nor_flash::NorFlash，读写都按 4 字节对齐，可以用内存模拟的 flash 测试
+const KV_MAGIC:u32 = 0x5754_4B56;
+const KV_HEADER_SIZE:u32 = 16;
+const ENTRY_HEADER_SIZE:u32 = 8;
+const ALIGN:u32 = 4;
+const BLANK_CHECK_SIZE:usize = 64;
+pub const KV_KEY_MAX:usize = 32;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum KvKind{
+    Removed = 0,
+    U32 = 1,
+    Bool = 2,
+    Str = 3,
+    Blob = 4,
+}
+
+impl KvKind {
+    fn from_u8(value:u8)->Option<Self>{
+        match value {
+            0 => Some(KvKind::Removed),
+            1 => Some(KvKind::U32),
+            2 => Some(KvKind::Bool),
+            3 => Some(KvKind::Str),
+            4 => Some(KvKind::Blob),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KvError<E>{
+    Storage(E),
+    Full,//整理后仍然放不下
+    TooLarge,//键或值超出长度限制
+    TypeMismatch,//保存的类型与读取的类型不一致
+}
+
+impl<E> From<E> for KvError<E> {
+    fn from(e: E) -> Self {
+        KvError::Storage(e)
+    }
+}
+
+//已读出的条目位置
+#[derive(Debug, Clone, Copy)]
+struct Entry{
+    pos:u32,//在当前一半中的位置
+    kind:KvKind,
+    key_len:u8,
+    value_len:u16,
+}
+
+impl Entry {
+    fn size(&self)->u32{
+        entry_size(self.key_len as usize, self.value_len as usize)
+    }
+}
+
+fn align(len:u32)->u32{
+    (len + ALIGN - 1) & !(ALIGN - 1)
+}
+
+fn entry_size(key_len:usize,value_len:usize)->u32{
+    align(ENTRY_HEADER_SIZE + key_len as u32 + value_len as u32)
+}
+
+fn encode_entry(kind:KvKind,key:&str,value:&[u8])->Vec<u8>{
+    let mut data = vec![0xFFu8; entry_size(key.len(), value.len()) as usize];
+    data[0] = kind as u8;
+    data[1] = key.len() as u8;
+    data[2..4].copy_from_slice(&(value.len() as u16).to_le_bytes());
+    let body_end = ENTRY_HEADER_SIZE as usize + key.len() + value.len();
+    data[ENTRY_HEADER_SIZE as usize..ENTRY_HEADER_SIZE as usize + key.len()].copy_from_slice(key.as_bytes());
+    data[ENTRY_HEADER_SIZE as usize + key.len()..body_end].copy_from_slice(value);
+    let crc = entry_crc(&data[..4], &data[ENTRY_HEADER_SIZE as usize..body_end]);
+    data[4..8].copy_from_slice(&crc.to_le_bytes());
+    data
+}
+
+fn entry_crc(head:&[u8],body:&[u8])->u32{
+    let mut data = Vec::with_capacity(head.len() + body.len());
+    data.extend_from_slice(head);
+    data.extend_from_slice(body);
+    crc32(&data)
+}
+
+pub struct KvStore<S>{
+    storage:S,
+    offset:u32,
+    half_size:u32,//按擦除大小对齐
+    active:Option<usize>,//正在使用的一半，两半都无效时为 None
+    sequence:u32,
+    end:u32,//下一条追加的位置
+    pending:bool,//begin_format 后还未写入头部
+    torn:bool,//末尾有写入中途断电的条目，这个位置不是空白，下次写入时先整理到另一半
+}
+
+impl<S:NorFlash> KvStore<S> {
+
+    //打开存储，读取两半的头部，使用序号较新的一半并找到末尾
+    //flash 的 READ_SIZE、WRITE_SIZE 不能超过 4，offset、half_size 要按 ERASE_SIZE 对齐
+    pub fn open(storage:S,offset:u32,half_size:u32)->Result<Self,KvError<S::Error>>{
+        let mut store = Self{
+            storage,
+            offset,
+            half_size,
+            active: None,
+            sequence: 0,
+            end: KV_HEADER_SIZE,
+            pending: false,
+            torn: false,
+        };
+        let a = store.read_header(0)?;
+        let b = store.read_header(1)?;
+        let newest = match (a,b) {
+            (Some(a),Some(b)) => if b.wrapping_sub(a) as i32 > 0 { Some((1,b)) } else { Some((0,a)) },
+            (Some(a),None) => Some((0,a)),
+            (None,Some(b)) => Some((1,b)),
+            (None,None) => None,
+        };
+        if let Some((half,sequence)) = newest {
+            store.active = Some(half);
+            store.sequence = sequence;
+            store.end = store.scan_end()?;
+            store.torn = !store.is_blank(store.end)?;
+        }
+        Ok(store)
+    }
+
+    //是否已经格式化过，没有时可能是空白 flash 或旧版本的数据
+    pub fn is_formatted(&self)->bool{
+        self.active.is_some() && !self.pending
+    }
+
+    fn half_addr(&self,half:usize)->u32{
+        self.offset + half as u32 * self.half_size
+    }
+
+    fn addr(&self,pos:u32)->u32{
+        self.half_addr(self.active.unwrap_or(0)) + pos
+    }
+
+    //从对齐的地址读取 len 字节，长度补齐到 4 字节读取后再截断
+    fn read_at(&mut self,addr:u32,len:usize)->Result<Vec<u8>,KvError<S::Error>>{
+        let mut data = vec![0u8; align(len as u32) as usize];
+        self.storage.read(addr, &mut data)?;
+        data.truncate(len);
+        Ok(data)
+    }
+
+    fn read_header(&mut self,half:usize)->Result<Option<u32>,KvError<S::Error>>{
+        let mut header = [0u8; KV_HEADER_SIZE as usize];
+        self.storage.read(self.half_addr(half), &mut header)?;
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let sequence = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let crc = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if magic != KV_MAGIC || crc32(&header[0..8]) != crc {
+            return Ok(None);
+        }
+        Ok(Some(sequence))
+    }
+
+    fn header_bytes(sequence:u32)->[u8; KV_HEADER_SIZE as usize]{
+        let mut header = [0xFFu8; KV_HEADER_SIZE as usize];
+        header[0..4].copy_from_slice(&KV_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&sequence.to_le_bytes());
+        let crc = crc32(&header[0..8]);
+        header[8..12].copy_from_slice(&crc.to_le_bytes());
+        header
+    }
+
+    //读取 pos 处条目的头部，空白或内容不完整时返回 None
+    fn read_entry(&mut self,pos:u32)->Result<Option<Entry>,KvError<S::Error>>{
+        if pos + ENTRY_HEADER_SIZE > self.half_size {
+            return Ok(None);
+        }
+        let mut head = [0u8; ENTRY_HEADER_SIZE as usize];
+        self.storage.read(self.addr(pos), &mut head)?;
+        let Some(kind) = KvKind::from_u8(head[0]) else { return Ok(None); };
+        let entry = Entry{
+            pos,
+            kind,
+            key_len: head[1],
+            value_len: u16::from_le_bytes([head[2],head[3]]),
+        };
+        if pos + entry.size() > self.half_size {
+            return Ok(None);
+        }
+        Ok(Some(entry))
+    }
+
+    //打开时逐条校验，遇到空白或 crc 不对(写入中途断电)的位置就是末尾
+    fn scan_end(&mut self)->Result<u32,KvError<S::Error>>{
+        let mut pos = KV_HEADER_SIZE;
+        while let Some(entry) = self.read_entry(pos)? {
+            let data = self.read_at(self.addr(pos), entry.size() as usize)?;
+            let body_end = ENTRY_HEADER_SIZE as usize + entry.key_len as usize + entry.value_len as usize;
+            let crc = u32::from_le_bytes(data[4..8].try_into().unwrap());
+            if entry_crc(&data[..4], &data[ENTRY_HEADER_SIZE as usize..body_end]) != crc {
+                break;
+            }
+            pos += entry.size();
+        }
+        Ok(pos)
+    }
+
+    //pos 之后是否都是擦除后的空白，断电时条目可能只写入了一部分
+    fn is_blank(&mut self,pos:u32)->Result<bool,KvError<S::Error>>{
+        let mut buffer = [0u8; BLANK_CHECK_SIZE];
+        let mut pos = pos;
+        while pos < self.half_size {
+            let len = (self.half_size - pos).min(BLANK_CHECK_SIZE as u32) as usize;
+            self.storage.read(self.addr(pos), &mut buffer[..len])?;
+            if buffer[..len].iter().any(|v| *v != 0xFF) {
+                return Ok(false);
+            }
+            pos += len as u32;
+        }
+        Ok(true)
+    }
+
+    fn read_key(&mut self,entry:&Entry)->Result<Vec<u8>,KvError<S::Error>>{
+        self.read_at(self.addr(entry.pos + ENTRY_HEADER_SIZE), entry.key_len as usize)
+    }
+
+    //所有条目，按写入顺序
+    fn entries(&mut self)->Result<Vec<Entry>,KvError<S::Error>>{
+        let mut result = Vec::new();
+        if self.active.is_none() {
+            return Ok(result);
+        }
+        let mut pos = KV_HEADER_SIZE;
+        while pos < self.end {
+            let Some(entry) = self.read_entry(pos)? else { break; };
+            if entry.key_len as usize <= KV_KEY_MAX {
+                result.push(entry);
+            }
+            pos += entry.size();
+        }
+        Ok(result)
+    }
+
+    //键的最新一条，已删除时返回 None
+    fn find(&mut self,key:&str)->Result<Option<Entry>,KvError<S::Error>>{
+        let mut found = None;
+        for entry in self.entries()? {
+            if entry.key_len as usize == key.len() && self.read_key(&entry)?.as_slice() == key.as_bytes() {
+                found = Some(entry);
+            }
+        }
+        Ok(found.filter(|v| v.kind != KvKind::Removed))
+    }
+
+    fn read_value(&mut self,entry:&Entry)->Result<Vec<u8>,KvError<S::Error>>{
+        let key_len = entry.key_len as usize;
+        let mut data = self.read_at(self.addr(entry.pos + ENTRY_HEADER_SIZE), key_len + entry.value_len as usize)?;
+        data.drain(..key_len);
+        Ok(data)
+    }
+
+    fn get(&mut self,key:&str,kind:KvKind)->Result<Option<Vec<u8>>,KvError<S::Error>>{
+        let Some(entry) = self.find(key)? else { return Ok(None); };
+        if entry.kind != kind {
+            return Err(KvError::TypeMismatch);
+        }
+        Ok(Some(self.read_value(&entry)?))
+    }
+
+    fn set(&mut self,key:&str,kind:KvKind,value:&[u8])->Result<(),KvError<S::Error>>{
+        if key.is_empty() || key.len() > KV_KEY_MAX || value.len() > u16::MAX as usize {
+            return Err(KvError::TooLarge);
+        }
+        //值没有变化时不写入，减少擦写
+        let unchanged = match self.find(key)? {
+            Some(entry) => entry.kind == kind && entry.value_len as usize == value.len() && self.read_value(&entry)? == value,
+            None => kind == KvKind::Removed,
+        };
+        if unchanged {
+            return Ok(());
+        }
+        let data = encode_entry(kind, key, value);
+        if self.active.is_some() && !self.torn && self.end + data.len() as u32 <= self.half_size {
+            let addr = self.addr(self.end);
+            //写入失败时这个位置可能已经写了一部分
+            self.torn = true;
+            self.storage.write(addr, &data)?;
+            self.torn = false;
+            self.end += data.len() as u32;
+            return Ok(());
+        }
+        if self.pending {
+            return Err(KvError::Full);
+        }
+        self.compact(Some((key,data)))
+    }
+
+    //把每个键的最新值整理到另一半，extra 为同时写入的新条目
+    //逐条复制，不需要整块的缓冲区
+    fn compact(&mut self,extra:Option<(&str,Vec<u8>)>)->Result<(),KvError<S::Error>>{
+        let extra_key = extra.as_ref().map(|v| v.0);
+        let mut live = Vec::new();
+        let mut size = KV_HEADER_SIZE;
+        for key in self.keys()? {
+            if Some(key.as_str()) == extra_key {
+                continue;
+            }
+            let Some(entry) = self.find(&key)? else { continue; };
+            size += entry.size();
+            live.push(entry);
+        }
+        size += extra.as_ref().map(|v| v.1.len() as u32).unwrap_or(0);
+        if size > self.half_size {
+            return Err(KvError::Full);
+        }
+
+        let half = match self.active {
+            Some(0) => 1,
+            _ => 0,
+        };
+        let target = self.half_addr(half);
+        self.storage.erase(target, target + self.half_size)?;
+        let mut pos = KV_HEADER_SIZE;
+        for entry in live.iter() {
+            let data = self.read_at(self.addr(entry.pos), entry.size() as usize)?;
+            self.storage.write(target + pos, &data)?;
+            pos += entry.size();
+        }
+        if let Some((_,data)) = extra {
+            self.storage.write(target + pos, &data)?;
+            pos += data.len() as u32;
+        }
+
+        //头部最后写入
+        let sequence = self.sequence.wrapping_add(1);
+        self.storage.write(target, &Self::header_bytes(sequence))?;
+        self.active = Some(half);
+        self.sequence = sequence;
+        self.end = pos;
+        self.torn = false;
+        Ok(())
+    }
+
+    //擦除 half 并开始写入，commit_format 写入头部前这一半无效，用于初始化和迁移
+    pub fn begin_format(&mut self,half:usize)->Result<(),KvError<S::Error>>{
+        let addr = self.half_addr(half);
+        self.storage.erase(addr, addr + self.half_size)?;
+        self.active = Some(half);
+        self.sequence = self.sequence.wrapping_add(1);
+        self.end = KV_HEADER_SIZE;
+        self.pending = true;
+        self.torn = false;
+        Ok(())
+    }
+
+    pub fn commit_format(&mut self)->Result<(),KvError<S::Error>>{
+        if !self.pending {
+            return Ok(());
+        }
+        let addr = self.addr(0);
+        self.storage.write(addr, &Self::header_bytes(self.sequence))?;
+        self.pending = false;
+        Ok(())
+    }
+
+    //没有使用中的一半时使用的位置
+    pub fn active_half(&self)->Option<usize>{
+        self.active
+    }
+
+    //所有未删除的键，按第一次写入的顺序
+    pub fn keys(&mut self)->Result<Vec<String>,KvError<S::Error>>{
+        let mut keys:Vec<(String,bool)> = Vec::new();
+        for entry in self.entries()? {
+            let key = self.read_key(&entry)?;
+            let Ok(key) = core::str::from_utf8(&key) else { continue; };
+            let live = entry.kind != KvKind::Removed;
+            match keys.iter_mut().find(|v| v.0 == key) {
+                Some(item) => item.1 = live,
+                None => keys.push((String::from(key),live)),
+            }
+        }
+        Ok(keys.into_iter().filter(|v| v.1).map(|v| v.0).collect())
+    }
+
+    pub fn contains(&mut self,key:&str)->Result<bool,KvError<S::Error>>{
+        Ok(self.find(key)?.is_some())
+    }
+
+    pub fn kind(&mut self,key:&str)->Result<Option<KvKind>,KvError<S::Error>>{
+        Ok(self.find(key)?.map(|v| v.kind))
+    }
+
+    pub fn remove(&mut self,key:&str)->Result<(),KvError<S::Error>>{
+        self.set(key, KvKind::Removed, &[])
+    }
+
+    pub fn get_u32(&mut self,key:&str)->Result<Option<u32>,KvError<S::Error>>{
+        Ok(self.get(key, KvKind::U32)?
+            .and_then(|v| v.as_slice().try_into().ok())
+            .map(u32::from_le_bytes))
+    }
+
+    pub fn set_u32(&mut self,key:&str,value:u32)->Result<(),KvError<S::Error>>{
+        self.set(key, KvKind::U32, &value.to_le_bytes())
+    }
+
+    pub fn get_bool(&mut self,key:&str)->Result<Option<bool>,KvError<S::Error>>{
+        Ok(self.get(key, KvKind::Bool)?.and_then(|v| v.first().map(|b| *b != 0)))
+    }
+
+    pub fn set_bool(&mut self,key:&str,value:bool)->Result<(),KvError<S::Error>>{
+        self.set(key, KvKind::Bool, &[value as u8])
+    }
+
+    //超出 N 或不是 utf8 时返回 TooLarge/TypeMismatch
+    pub fn get_str<const N:usize>(&mut self,key:&str)->Result<Option<heapless::String<N>>,KvError<S::Error>>{
+        let Some(value) = self.get(key, KvKind::Str)? else { return Ok(None); };
+        let text = core::str::from_utf8(&value).map_err(|_| KvError::TypeMismatch)?;
+        Ok(Some(heapless::String::from_str(text).map_err(|_| KvError::TooLarge)?))
+    }
+
+    pub fn set_str(&mut self,key:&str,value:&str)->Result<(),KvError<S::Error>>{
+        self.set(key, KvKind::Str, value.as_bytes())
+    }
+
+    pub fn get_blob(&mut self,key:&str)->Result<Option<Vec<u8>>,KvError<S::Error>>{
+        self.get(key, KvKind::Blob)
+    }
+
+    pub fn set_blob(&mut self,key:&str,value:&[u8])->Result<(),KvError<S::Error>>{
+        self.set(key, KvKind::Blob, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+    const SECTOR:usize = 4096;
+    const HALF:u32 = SECTOR as u32;
+
+    #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+    struct MockError(NorFlashErrorKind);
+
+    impl NorFlashError for MockError {
+        fn kind(&self) -> NorFlashErrorKind {
+            self.0
+        }
+    }
+
+    //内存模拟的 NOR flash：擦除后为 0xFF，写入只能把 1 变成 0
+    //budget 为还能写入的字节数，用完后写入失败，模拟写入中途断电
+    struct MockFlash{
+        data:Vec<u8>,
+        budget:Option<usize>,
+        erases:usize,
+    }
+
+    impl MockFlash {
+        fn new()->Self{
+            Self{ data: vec![0xFF; 2 * SECTOR], budget: None, erases: 0 }
+        }
+
+        fn check(&self,offset:u32,len:usize,align:usize)->Result<(),MockError>{
+            if !(offset as usize).is_multiple_of(align) || !len.is_multiple_of(align) {
+                return Err(MockError(NorFlashErrorKind::NotAligned));
+            }
+            if offset as usize + len > self.data.len() {
+                return Err(MockError(NorFlashErrorKind::OutOfBounds));
+            }
+            Ok(())
+        }
+    }
+
+    impl ErrorType for MockFlash {
+        type Error = MockError;
+    }
+
+    impl ReadNorFlash for MockFlash {
+        const READ_SIZE: usize = 4;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            self.check(offset, bytes.len(), Self::READ_SIZE)?;
+            bytes.copy_from_slice(&self.data[offset as usize..offset as usize + bytes.len()]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for MockFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = SECTOR;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            self.check(from, (to - from) as usize, Self::ERASE_SIZE)?;
+            self.data[from as usize..to as usize].fill(0xFF);
+            self.erases += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            self.check(offset, bytes.len(), Self::WRITE_SIZE)?;
+            for (index,byte) in bytes.iter().enumerate() {
+                if let Some(budget) = self.budget.as_mut() {
+                    if *budget == 0 {
+                        return Err(MockError(NorFlashErrorKind::Other));
+                    }
+                    *budget -= 1;
+                }
+                self.data[offset as usize + index] &= *byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn open(flash:&mut MockFlash)->KvStore<&mut MockFlash>{
+        KvStore::open(flash, 0, HALF).unwrap()
+    }
+
+    fn formatted(flash:&mut MockFlash)->KvStore<&mut MockFlash>{
+        let mut kv = open(flash);
+        kv.begin_format(0).unwrap();
+        kv.commit_format().unwrap();
+        kv
+    }
+
+    #[test]
+    fn open_blank_flash(){
+        let mut flash = MockFlash::new();
+        let mut kv = open(&mut flash);
+        assert!(!kv.is_formatted());
+        assert_eq!(kv.active_half(), None);
+        assert_eq!(kv.get_u32("a").unwrap(), None);
+        assert!(kv.keys().unwrap().is_empty());
+
+        kv.begin_format(1).unwrap();
+        assert!(!kv.is_formatted());
+        kv.commit_format().unwrap();
+        assert!(kv.is_formatted());
+
+        let kv = open(&mut flash);
+        assert!(kv.is_formatted());
+        assert_eq!(kv.active_half(), Some(1));
+    }
+
+    #[test]
+    fn set_and_get_each_type(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_u32("count", 0x1234_5678).unwrap();
+        kv.set_bool("enabled", true).unwrap();
+        kv.set_str("name", "计时器").unwrap();
+        kv.set_blob("data", &[1, 2, 3, 4, 5]).unwrap();
+
+        let mut kv = open(&mut flash);
+        assert_eq!(kv.get_u32("count").unwrap(), Some(0x1234_5678));
+        assert_eq!(kv.get_bool("enabled").unwrap(), Some(true));
+        assert_eq!(kv.get_str::<16>("name").unwrap().as_deref(), Some("计时器"));
+        assert_eq!(kv.get_blob("data").unwrap(), Some(vec![1, 2, 3, 4, 5]));
+        assert_eq!(kv.kind("name").unwrap(), Some(KvKind::Str));
+        assert!(matches!(kv.get_u32("name"), Err(KvError::TypeMismatch)));
+        assert!(matches!(kv.get_str::<4>("name"), Err(KvError::TooLarge)));
+        assert!(matches!(kv.set_u32("", 1), Err(KvError::TooLarge)));
+        assert!(matches!(kv.set_u32(&"k".repeat(KV_KEY_MAX + 1), 1), Err(KvError::TooLarge)));
+    }
+
+    #[test]
+    fn overwrite_keeps_latest(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_str("ssid", "home").unwrap();
+        kv.set_str("ssid", "office").unwrap();
+        kv.set_u32("ssid", 7).unwrap();
+        assert_eq!(kv.get_u32("ssid").unwrap(), Some(7));
+
+        //值相同时不追加
+        let end = kv.end;
+        kv.set_u32("ssid", 7).unwrap();
+        assert_eq!(kv.end, end);
+
+        let mut kv = open(&mut flash);
+        assert_eq!(kv.get_u32("ssid").unwrap(), Some(7));
+        assert_eq!(kv.keys().unwrap(), vec![String::from("ssid")]);
+    }
+
+    #[test]
+    fn remove_key(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_u32("a", 1).unwrap();
+        kv.set_u32("b", 2).unwrap();
+        kv.remove("a").unwrap();
+        assert_eq!(kv.get_u32("a").unwrap(), None);
+        assert!(!kv.contains("a").unwrap());
+        assert!(kv.contains("b").unwrap());
+
+        //不存在的键不写入
+        let end = kv.end;
+        kv.remove("missing").unwrap();
+        assert_eq!(kv.end, end);
+
+        let mut kv = open(&mut flash);
+        assert_eq!(kv.get_u32("a").unwrap(), None);
+        assert_eq!(kv.get_u32("b").unwrap(), Some(2));
+    }
+
+    #[test]
+    fn keys_in_first_write_order(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_u32("c", 1).unwrap();
+        kv.set_u32("a", 1).unwrap();
+        kv.set_u32("b", 1).unwrap();
+        kv.set_u32("c", 2).unwrap();
+        kv.remove("a").unwrap();
+        assert_eq!(kv.keys().unwrap(), vec![String::from("c"), String::from("b")]);
+        kv.set_u32("a", 3).unwrap();
+        assert_eq!(kv.keys().unwrap(), vec![String::from("c"), String::from("a"), String::from("b")]);
+    }
+
+    #[test]
+    fn compacts_into_other_half_when_full(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_str("keep", "value").unwrap();
+        kv.set_u32("gone", 1).unwrap();
+        kv.remove("gone").unwrap();
+        for i in 0..400u32 {
+            kv.set_u32("counter", i).unwrap();
+        }
+        assert_eq!(kv.active_half(), Some(1));
+        assert_eq!(kv.get_u32("counter").unwrap(), Some(399));
+        assert_eq!(kv.get_str::<8>("keep").unwrap().as_deref(), Some("value"));
+        assert_eq!(kv.keys().unwrap(), vec![String::from("keep"), String::from("counter")]);
+        let sequence = kv.sequence;
+
+        let mut kv = open(&mut flash);
+        assert_eq!(kv.active_half(), Some(1));
+        assert_eq!(kv.sequence, sequence);
+        assert_eq!(kv.get_u32("counter").unwrap(), Some(399));
+        assert_eq!(kv.get_str::<8>("keep").unwrap().as_deref(), Some("value"));
+    }
+
+    #[test]
+    fn full_after_compaction(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        let value = [0u8; 1000];
+        for key in ["a", "b", "c", "d"] {
+            kv.set_blob(key, &value).unwrap();
+        }
+        assert!(matches!(kv.set_blob("e", &value), Err(KvError::Full)));
+        assert_eq!(kv.get_blob("d").unwrap().map(|v| v.len()), Some(1000));
+    }
+
+    #[test]
+    fn appends_without_erasing(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        for i in 0..20u32 {
+            kv.set_u32("counter", i).unwrap();
+        }
+        assert_eq!(flash.erases, 1);
+    }
+
+    #[test]
+    fn recovers_from_torn_last_entry(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_str("wifi", "home").unwrap();
+        kv.set_u32("counter", 1).unwrap();
+
+        //只写入了条目的前半部分
+        flash.budget = Some(10);
+        let mut kv = open(&mut flash);
+        assert!(kv.set_str("wifi", "office").is_err());
+        flash.budget = None;
+
+        let mut kv = open(&mut flash);
+        assert!(kv.torn);
+        assert_eq!(kv.active_half(), Some(0));
+        assert_eq!(kv.get_str::<8>("wifi").unwrap().as_deref(), Some("home"));
+        assert_eq!(kv.get_u32("counter").unwrap(), Some(1));
+
+        //末尾不是空白，下一次写入整理到另一半
+        kv.set_u32("counter", 2).unwrap();
+        assert!(!kv.torn);
+        assert_eq!(kv.active_half(), Some(1));
+
+        let mut kv = open(&mut flash);
+        assert!(!kv.torn);
+        assert_eq!(kv.get_str::<8>("wifi").unwrap().as_deref(), Some("home"));
+        assert_eq!(kv.get_u32("counter").unwrap(), Some(2));
+    }
+
+    #[test]
+    fn interrupted_compaction_keeps_old_half(){
+        let mut flash = MockFlash::new();
+        let mut kv = formatted(&mut flash);
+        kv.set_str("wifi", "home").unwrap();
+        let value = [7u8; 1000];
+        for key in ["a", "b", "c", "d"] {
+            kv.set_blob(key, &value).unwrap();
+        }
+
+        //整理时写到一半断电，新的一半没有头部
+        flash.budget = Some(2000);
+        let mut kv = open(&mut flash);
+        assert!(kv.set_blob("a", &[1u8; 1000]).is_err());
+        flash.budget = None;
+
+        let mut kv = open(&mut flash);
+        assert_eq!(kv.active_half(), Some(0));
+        assert_eq!(kv.get_str::<8>("wifi").unwrap().as_deref(), Some("home"));
+        assert_eq!(kv.get_blob("a").unwrap(), Some(vec![7u8; 1000]));
+    }
+}
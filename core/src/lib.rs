@@ -0,0 +1,10 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod record;
+pub mod kv;
+pub mod dhcp;
+pub mod mdns;
+pub mod http;
+pub mod interval;
@@ -0,0 +1,468 @@
+use heapless::{String, Vec};
+
+//mDNS 响应，局域网内通过 <设备名>.local 访问，并用 DNS-SD 发布网页配置服务
+//handle 只处理报文，不涉及网络收发，组播组和 socket 由调用者管理
+//网页配置服务只在运行期间发布，停止后用 goodbye 通知其他主机删除缓存
+pub const MDNS_PORT:u16 = 5353;
+pub const MDNS_ADDR:[u8;4] = [224,0,0,251];
+pub const MDNS_PACKET_SIZE:usize = 512;
+pub const DEFAULT_DEVICE_NAME:&str = "work-timer";
+pub const DEVICE_NAME_MAX:usize = 32;
+
+const HEADER_SIZE:usize = 12;
+const TYPE_A:u16 = 1;
+const TYPE_PTR:u16 = 12;
+const TYPE_TXT:u16 = 16;
+const TYPE_SRV:u16 = 33;
+const TYPE_ANY:u16 = 255;
+const CLASS_IN:u16 = 1;
+const CACHE_FLUSH:u16 = 0x8000;//记录只属于本机，其他主机收到时替换缓存
+const UNICAST_RESPONSE:u16 = 0x8000;//问题类别的最高位，要求单播回复
+const HOST_TTL:u32 = 120;
+const SERVICE_TTL:u32 = 4500;
+const QUESTION_MAX:usize = 4;
+
+const LOCAL:&str = "local";
+const SERVICE_TYPE:&str = "_http._tcp.local";
+const SERVICE_ENUM:&str = "_services._dns-sd._udp.local";
+const HTTP_PORT:u16 = 80;
+const TXT_PATH:&str = "path=/config";
+
+//回复的发送方式，查询要求单播或是普通 DNS 客户端发来的查询时回复给发送方
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MdnsReply{
+    Multicast,
+    Unicast,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Record{
+    Host,//<设备名>.local 的 A 记录
+    Service,//_http._tcp.local 指向服务实例的 PTR 记录
+    Srv,//服务实例的地址和端口
+    Txt,//服务实例的附加信息
+    ServiceEnum,//列出本机提供的服务类型
+}
+
+//设备名作为域名的一段，只能用字母、数字和 -，不能以 - 开头或结尾
+pub fn is_valid_name(name:&str)->bool{
+    !name.is_empty() && name.len() <= DEVICE_NAME_MAX
+        && !name.starts_with('-') && !name.ends_with('-')
+        && name.bytes().all(|v| v.is_ascii_alphanumeric() || v == b'-')
+}
+
+//查询中的一个问题
+struct Question{
+    name:String<128>,
+    qtype:u16,
+    unicast:bool,
+}
+
+//按顺序写入记录，超出长度时返回 None
+struct Writer<'a>{
+    buffer:&'a mut Vec<u8,MDNS_PACKET_SIZE>,
+}
+
+impl<'a> Writer<'a> {
+    fn bytes(&mut self,data:&[u8])->Option<()>{
+        self.buffer.extend_from_slice(data).ok()
+    }
+
+    fn u16(&mut self,value:u16)->Option<()>{
+        self.bytes(&value.to_be_bytes())
+    }
+
+    fn u32(&mut self,value:u32)->Option<()>{
+        self.bytes(&value.to_be_bytes())
+    }
+
+    //依次写入各部分的标签，不压缩
+    fn name(&mut self,parts:&[&str])->Option<()>{
+        for part in parts.iter() {
+            for label in part.split('.').filter(|v| !v.is_empty()) {
+                if label.len() > 63 {
+                    return None;
+                }
+                self.bytes(&[label.len() as u8])?;
+                self.bytes(label.as_bytes())?;
+            }
+        }
+        self.bytes(&[0])
+    }
+
+    //写入数据部分，先占位长度，写完后回填
+    fn rdata(&mut self,write:impl FnOnce(&mut Self)->Option<()>)->Option<()>{
+        let pos = self.buffer.len();
+        self.u16(0)?;
+        write(self)?;
+        let len = (self.buffer.len() - pos - 2) as u16;
+        self.buffer[pos..pos + 2].copy_from_slice(&len.to_be_bytes());
+        Some(())
+    }
+}
+
+pub struct Mdns<'a>{
+    name:&'a str,
+    ip:[u8;4],
+    http:bool,//是否发布网页配置服务
+}
+
+const SERVICE_RECORDS:[Record;4] = [Record::Service, Record::Srv, Record::Txt, Record::ServiceEnum];
+
+impl<'a> Mdns<'a> {
+    pub fn new(name:&'a str,ip:[u8;4],http:bool)->Self{
+        Self{ name, ip, http }
+    }
+
+    //name 是否为 <设备名>.<suffix>，不区分大小写
+    fn is_under(&self,name:&str,suffix:&str)->bool{
+        let name = name.as_bytes();
+        let prefix = self.name.len();
+        name.len() == prefix + suffix.len() + 1
+            && name[..prefix].eq_ignore_ascii_case(self.name.as_bytes())
+            && name[prefix] == b'.'
+            && name[prefix + 1..].eq_ignore_ascii_case(suffix.as_bytes())
+    }
+
+    //问题对应的回答和附加记录
+    fn answer(&self,question:&Question,answers:&mut Vec<Record,5>,additional:&mut Vec<Record,5>){
+        let name = question.name.as_str();
+        let any = question.qtype == TYPE_ANY;
+        let add = |list:&mut Vec<Record,5>,record:Record| {
+            if !list.contains(&record) {
+                let _ = list.push(record);
+            }
+        };
+        if self.is_under(name, LOCAL) {
+            if any || question.qtype == TYPE_A {
+                add(answers, Record::Host);
+            }
+        } else if !self.http {
+            //服务未运行时不回答服务相关的查询
+        } else if name.eq_ignore_ascii_case(SERVICE_TYPE) {
+            if any || question.qtype == TYPE_PTR {
+                add(answers, Record::Service);
+                add(additional, Record::Srv);
+                add(additional, Record::Txt);
+                add(additional, Record::Host);
+            }
+        } else if self.is_under(name, SERVICE_TYPE) {
+            if any || question.qtype == TYPE_SRV {
+                add(answers, Record::Srv);
+                add(additional, Record::Host);
+            }
+            if any || question.qtype == TYPE_TXT {
+                add(answers, Record::Txt);
+            }
+        } else if name.eq_ignore_ascii_case(SERVICE_ENUM) && (any || question.qtype == TYPE_PTR) {
+            add(answers, Record::ServiceEnum);
+        }
+    }
+
+    //goodbye 时 TTL 为 0，收到的主机会删除对应的缓存
+    fn record(&self,writer:&mut Writer,record:Record,goodbye:bool)->Option<()>{
+        let ttl = |value:u32| if goodbye { 0 } else { value };
+        match record {
+            Record::Host => {
+                writer.name(&[self.name, LOCAL])?;
+                writer.u16(TYPE_A)?;
+                writer.u16(CLASS_IN | CACHE_FLUSH)?;
+                writer.u32(ttl(HOST_TTL))?;
+                writer.rdata(|w| w.bytes(&self.ip))
+            }
+            Record::Service => {
+                writer.name(&[SERVICE_TYPE])?;
+                writer.u16(TYPE_PTR)?;
+                writer.u16(CLASS_IN)?;
+                writer.u32(ttl(SERVICE_TTL))?;
+                writer.rdata(|w| w.name(&[self.name, SERVICE_TYPE]))
+            }
+            Record::Srv => {
+                writer.name(&[self.name, SERVICE_TYPE])?;
+                writer.u16(TYPE_SRV)?;
+                writer.u16(CLASS_IN | CACHE_FLUSH)?;
+                writer.u32(ttl(HOST_TTL))?;
+                writer.rdata(|w| {
+                    w.u16(0)?;//优先级
+                    w.u16(0)?;//权重
+                    w.u16(HTTP_PORT)?;
+                    w.name(&[self.name, LOCAL])
+                })
+            }
+            Record::Txt => {
+                writer.name(&[self.name, SERVICE_TYPE])?;
+                writer.u16(TYPE_TXT)?;
+                writer.u16(CLASS_IN | CACHE_FLUSH)?;
+                writer.u32(ttl(SERVICE_TTL))?;
+                writer.rdata(|w| {
+                    w.bytes(&[TXT_PATH.len() as u8])?;
+                    w.bytes(TXT_PATH.as_bytes())
+                })
+            }
+            Record::ServiceEnum => {
+                writer.name(&[SERVICE_ENUM])?;
+                writer.u16(TYPE_PTR)?;
+                writer.u16(CLASS_IN)?;
+                writer.u32(ttl(SERVICE_TTL))?;
+                writer.rdata(|w| w.name(&[SERVICE_TYPE]))
+            }
+        }
+    }
+
+    fn write(&self,id:u16,questions:&[Question],answers:&[Record],additional:&[Record],goodbye:bool,response:&mut Vec<u8,MDNS_PACKET_SIZE>)->Option<()>{
+        response.clear();
+        let mut writer = Writer{ buffer: response };
+        writer.u16(id)?;
+        writer.bytes(&[0x84, 0x00])?;// 标志：响应，权威回答
+        writer.u16(questions.len() as u16)?;
+        writer.u16(answers.len() as u16)?;
+        writer.u16(0)?;
+        writer.u16(additional.len() as u16)?;
+        for question in questions.iter() {
+            writer.name(&[question.name.as_str()])?;
+            writer.u16(question.qtype)?;
+            writer.u16(CLASS_IN)?;
+        }
+        for record in answers.iter().chain(additional.iter()) {
+            self.record(&mut writer, *record, goodbye)?;
+        }
+        Some(())
+    }
+
+    //处理一个查询，需要回复时把回复写入 response 并返回发送方式
+    //legacy 为发送端口不是 5353 的普通 DNS 查询，回复要带上 ID 和问题
+    pub fn handle(&self,request:&[u8],legacy:bool,response:&mut Vec<u8,MDNS_PACKET_SIZE>)->Option<MdnsReply>{
+        if request.len() < HEADER_SIZE || request[2] & 0x80 != 0 {
+            return None;
+        }
+        let count = u16::from_be_bytes([request[4], request[5]]) as usize;
+        let mut questions:Vec<Question,QUESTION_MAX> = Vec::new();
+        let mut pos = HEADER_SIZE;
+        for _ in 0..count.min(QUESTION_MAX) {
+            let (name,next) = read_name(request, pos)?;
+            let qtype = u16::from_be_bytes([*request.get(next)?, *request.get(next + 1)?]);
+            let qclass = u16::from_be_bytes([*request.get(next + 2)?, *request.get(next + 3)?]);
+            pos = next + 4;
+            let _ = questions.push(Question{ name, qtype, unicast: qclass & UNICAST_RESPONSE != 0 });
+        }
+
+        let mut answers = Vec::new();
+        let mut additional = Vec::new();
+        for question in questions.iter() {
+            self.answer(question, &mut answers, &mut additional);
+        }
+        if answers.is_empty() {
+            return None;
+        }
+        additional.retain(|v| !answers.contains(v));
+
+        if legacy {
+            let id = u16::from_be_bytes([request[0], request[1]]);
+            questions.retain(|question| {
+                let mut list = Vec::new();
+                self.answer(question, &mut list, &mut Vec::new());
+                !list.is_empty()
+            });
+            self.write(id, &questions, &answers, &additional, false, response)?;
+            Some(MdnsReply::Unicast)
+        } else {
+            self.write(0, &[], &answers, &additional, false, response)?;
+            if questions.iter().any(|v| v.unicast) {
+                Some(MdnsReply::Unicast)
+            } else {
+                Some(MdnsReply::Multicast)
+            }
+        }
+    }
+
+    //加入网络或地址、设备名变化时主动发布所有记录
+    pub fn announce(&self,response:&mut Vec<u8,MDNS_PACKET_SIZE>)->Option<()>{
+        let mut records:Vec<Record,5> = Vec::new();
+        let _ = records.push(Record::Host);
+        if self.http {
+            let _ = records.extend_from_slice(&SERVICE_RECORDS);
+        }
+        self.write(0, &[], &records, &[], false, response)
+    }
+
+    //网页配置服务停止或地址、设备名变化前撤销服务记录，没有发布服务时返回 None
+    pub fn goodbye(&self,response:&mut Vec<u8,MDNS_PACKET_SIZE>)->Option<()>{
+        if !self.http {
+            return None;
+        }
+        self.write(0, &[], &SERVICE_RECORDS, &[], true, response)
+    }
+}
+
+//读取域名，支持压缩指针，返回用 . 连接的域名和之后的位置
+fn read_name(packet:&[u8],start:usize)->Option<(String<128>,usize)>{
+    let mut name = String::new();
+    let mut pos = start;
+    let mut next = None;
+    let mut jumps = 0;
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((name, next.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            //指针只能向前，限制次数避免循环
+            jumps += 1;
+            if jumps > 8 {
+                return None;
+            }
+            let target = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            if target >= pos {
+                return None;
+            }
+            next.get_or_insert(pos + 2);
+            pos = target;
+            continue;
+        }
+        let label = core::str::from_utf8(packet.get(pos + 1..pos + 1 + len)?).ok()?;
+        if !name.is_empty() {
+            name.push('.').ok()?;
+        }
+        name.push_str(label).ok()?;
+        pos += len + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP:[u8;4] = [192,168,2,10];
+
+    //抓包得到的查询，头部：ID、标志、问题数、回答数、授权数、附加数
+    //ping timer.local，查询 A 记录
+    const QUERY_A:&str = "0000000000010000000000000574696d6572056c6f63616c0000010001";
+    //大小写不同的名称，类别的最高位要求单播回复
+    const QUERY_A_UPPER_QU:&str = "0000000000010000000000000554494d4552054c6f63616c0000018001";
+    //nslookup 从普通端口发出的查询，ID 0x1234，要求递归
+    const QUERY_LEGACY:&str = "1234010000010000000000000574696d6572056c6f63616c0000010001";
+    //两个问题，第二个问题的名称用指针指向第一个问题中的 local
+    const QUERY_COMPRESSED:&str = "000000000002000000000000056c6f63616c00000100010574696d6572c00c00010001";
+    //名称的指针指向自己
+    const QUERY_LOOP:&str = "000000000001000000000000c00c00010001";
+    //名称的指针指向后面
+    const QUERY_FORWARD:&str = "000000000001000000000000c01000010001056c6f63616c00";
+    //其他主机发出的回复，不处理
+    const RESPONSE:&str = "0000840000000001000000000574696d6572056c6f63616c000001800100000078000400c0a8020a";
+
+    fn hex(text:&str)->Vec<u8,MDNS_PACKET_SIZE>{
+        let text:heapless::String<1024> = text.chars().filter(|v| !v.is_whitespace()).collect();
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    //查询 _http._tcp.local 的 PTR 记录
+    fn query_service()->Vec<u8,MDNS_PACKET_SIZE>{
+        let mut request = Vec::new();
+        request.extend_from_slice(&[0,0,0,0,0,1,0,0,0,0,0,0]).unwrap();
+        let mut writer = Writer{ buffer: &mut request };
+        writer.name(&[SERVICE_TYPE]).unwrap();
+        writer.u16(TYPE_PTR).unwrap();
+        writer.u16(CLASS_IN).unwrap();
+        request
+    }
+
+    fn count(packet:&[u8],offset:usize)->u16{
+        u16::from_be_bytes([packet[offset], packet[offset + 1]])
+    }
+
+    #[test]
+    fn service_published_while_running(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, true);
+        assert_eq!(mdns.handle(&query_service(), false, &mut response), Some(MdnsReply::Multicast));
+        mdns.announce(&mut response).unwrap();
+        assert_eq!(count(&response, 6), 5);
+    }
+
+    #[test]
+    fn service_hidden_when_stopped(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, false);
+        assert_eq!(mdns.handle(&query_service(), false, &mut response), None);
+        mdns.announce(&mut response).unwrap();
+        assert_eq!(count(&response, 6), 1);
+        assert!(mdns.goodbye(&mut response).is_none());
+    }
+
+    #[test]
+    fn goodbye_has_zero_ttl(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, true);
+        mdns.goodbye(&mut response).unwrap();
+        assert_eq!(count(&response, 6), 4);
+        //第一条记录：名称之后是类型、类别和 TTL
+        let (name,pos) = read_name(&response, HEADER_SIZE).unwrap();
+        assert_eq!(name.as_str(), SERVICE_TYPE);
+        assert_eq!(&response[pos + 4..pos + 8], &[0,0,0,0]);
+    }
+
+    #[test]
+    fn a_query(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, false);
+        assert_eq!(mdns.handle(&hex(QUERY_A), false, &mut response), Some(MdnsReply::Multicast));
+        //组播回复不带问题，只有一条 A 记录，数据是地址
+        assert_eq!(count(&response, 4), 0);
+        assert_eq!(count(&response, 6), 1);
+        assert_eq!(&response[response.len() - 4..], &IP);
+
+        let other = Mdns::new("other", IP, false);
+        assert_eq!(other.handle(&hex(QUERY_A), false, &mut response), None);
+    }
+
+    #[test]
+    fn names_are_case_insensitive(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, false);
+        assert_eq!(mdns.handle(&hex(QUERY_A_UPPER_QU), false, &mut response), Some(MdnsReply::Unicast));
+        assert_eq!(count(&response, 6), 1);
+    }
+
+    #[test]
+    fn legacy_query_echoes_id_and_question(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, false);
+        assert_eq!(mdns.handle(&hex(QUERY_LEGACY), true, &mut response), Some(MdnsReply::Unicast));
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!(count(&response, 4), 1);
+        assert_eq!(count(&response, 6), 1);
+        let (name,_) = read_name(&response, HEADER_SIZE).unwrap();
+        assert_eq!(name.as_str(), "timer.local");
+    }
+
+    #[test]
+    fn compressed_names(){
+        let query = hex(QUERY_COMPRESSED);
+        let (name,next) = read_name(&query, HEADER_SIZE).unwrap();
+        assert_eq!(name.as_str(), "local");
+        let (name,next) = read_name(&query, next + 4).unwrap();
+        assert_eq!(name.as_str(), "timer.local");
+        //指针之后是问题的类型和类别
+        assert_eq!(next + 4, query.len());
+
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, false);
+        assert_eq!(mdns.handle(&query, false, &mut response), Some(MdnsReply::Multicast));
+        assert_eq!(count(&response, 6), 1);
+    }
+
+    #[test]
+    fn malformed_packets_are_ignored(){
+        let mut response = Vec::new();
+        let mdns = Mdns::new("timer", IP, true);
+        let query = hex(QUERY_A);
+        for len in 0..query.len() {
+            assert_eq!(mdns.handle(&query[..len], false, &mut response), None);
+        }
+        assert!(read_name(&hex(QUERY_LOOP), HEADER_SIZE).is_none());
+        assert_eq!(mdns.handle(&hex(QUERY_LOOP), false, &mut response), None);
+        assert!(read_name(&hex(QUERY_FORWARD), HEADER_SIZE).is_none());
+        assert_eq!(mdns.handle(&hex(QUERY_FORWARD), false, &mut response), None);
+        assert_eq!(mdns.handle(&hex(RESPONSE), false, &mut response), None);
+    }
+}
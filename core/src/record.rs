@@ -0,0 +1,213 @@
+use core::str::FromStr;
+use heapless::{String, Vec};
+
+//存储记录的格式：头部 + 内容
+//头部依次为 magic(u32)、结构版本(u16)、内容长度(u16)、内容 crc32(u32)，都按小端保存
+//内容由各结构体按固定顺序逐个字段写入，与结构体的内存布局无关
+pub const RECORD_MAGIC:u32 = 0x5754_5243;
+pub const RECORD_HEADER_SIZE:usize = 12;
+
+//按字段写入，超出缓冲区时标记溢出，由 pack 返回失败
+pub struct Encoder<'a>{
+    buffer:&'a mut [u8],
+    pos:usize,
+    overflow:bool,
+}
+
+impl<'a> Encoder<'a> {
+    pub fn new(buffer:&'a mut [u8])->Self{
+        Self{ buffer, pos: 0, overflow: false }
+    }
+
+    pub fn bytes(&mut self,data:&[u8]){
+        let end = self.pos + data.len();
+        if end > self.buffer.len() {
+            self.overflow = true;
+            return;
+        }
+        self.buffer[self.pos..end].copy_from_slice(data);
+        self.pos = end;
+    }
+
+    pub fn u8(&mut self,value:u8){
+        self.bytes(&[value]);
+    }
+
+    pub fn bool(&mut self,value:bool){
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self,value:u16){
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self,value:u32){
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self,value:u64){
+        self.bytes(&value.to_le_bytes());
+    }
+
+    //字符串先写一个字节的长度
+    pub fn str(&mut self,value:&str){
+        if value.len() > u8::MAX as usize {
+            self.overflow = true;
+            return;
+        }
+        self.u8(value.len() as u8);
+        self.bytes(value.as_bytes());
+    }
+
+    //列表先写一个字节的数量
+    pub fn list<T:Encode>(&mut self,items:&[T]){
+        if items.len() > u8::MAX as usize {
+            self.overflow = true;
+            return;
+        }
+        self.u8(items.len() as u8);
+        for item in items.iter() {
+            item.encode(self);
+        }
+    }
+
+    pub fn finish(self)->Option<usize>{
+        if self.overflow {
+            None
+        }else{
+            Some(self.pos)
+        }
+    }
+}
+
+//按写入的顺序读取字段，数据不够或内容不合法时返回 None
+pub struct Decoder<'a>{
+    data:&'a [u8],
+    pos:usize,
+    pub schema:u16,//记录保存时的结构版本，字段有增减时按它判断
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data:&'a [u8],schema:u16)->Self{
+        Self{ data, pos: 0, schema }
+    }
+
+    pub fn bytes(&mut self,len:usize)->Option<&'a [u8]>{
+        let end = self.pos.checked_add(len)?;
+        let data = self.data.get(self.pos..end)?;
+        self.pos = end;
+        Some(data)
+    }
+
+    pub fn u8(&mut self)->Option<u8>{
+        Some(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self)->Option<bool>{
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    pub fn u16(&mut self)->Option<u16>{
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().ok()?))
+    }
+
+    pub fn u32(&mut self)->Option<u32>{
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().ok()?))
+    }
+
+    pub fn u64(&mut self)->Option<u64>{
+        Some(u64::from_le_bytes(self.bytes(8)?.try_into().ok()?))
+    }
+
+    pub fn string<const N:usize>(&mut self)->Option<String<N>>{
+        let len = self.u8()? as usize;
+        let text = core::str::from_utf8(self.bytes(len)?).ok()?;
+        String::from_str(text).ok()
+    }
+
+    pub fn list<T:Encode,const N:usize>(&mut self)->Option<Vec<T,N>>{
+        let len = self.u8()? as usize;
+        if len > N {
+            return None;
+        }
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(T::decode(self)?).ok()?;
+        }
+        Some(items)
+    }
+}
+
+pub trait Encode: Sized {
+    //结构版本，字段有变化时加一，并在 decode 中按 decoder.schema 兼容旧数据
+    const SCHEMA:u16 = 1;
+
+    fn encode(&self,encoder:&mut Encoder);
+
+    fn decode(decoder:&mut Decoder)->Option<Self>;
+}
+
+//crc32 (IEEE)，按位计算，记录都很小不需要查表
+pub fn crc32(data:&[u8])->u32{
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data.iter() {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+//把记录写入 buffer，返回总长度，buffer 不够时返回 None
+pub fn pack<T:Encode>(record:&T,buffer:&mut [u8])->Option<usize>{
+    if buffer.len() < RECORD_HEADER_SIZE {
+        return None;
+    }
+    let (header,body) = buffer.split_at_mut(RECORD_HEADER_SIZE);
+    let mut encoder = Encoder::new(body);
+    record.encode(&mut encoder);
+    let len = encoder.finish()?;
+    if len > u16::MAX as usize {
+        return None;
+    }
+    let crc = crc32(&body[..len]);
+    header[0..4].copy_from_slice(&RECORD_MAGIC.to_le_bytes());
+    header[4..6].copy_from_slice(&T::SCHEMA.to_le_bytes());
+    header[6..8].copy_from_slice(&(len as u16).to_le_bytes());
+    header[8..12].copy_from_slice(&crc.to_le_bytes());
+    Some(RECORD_HEADER_SIZE + len)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum UnpackError{
+    Empty,//没有记录，flash 未写入或是旧版本的数据
+    Corrupt,//长度或 crc 不对，写入时断电等
+    Unsupported,//比当前固件新的结构版本
+}
+
+pub fn unpack<T:Encode>(data:&[u8])->Result<T,UnpackError>{
+    if data.len() < RECORD_HEADER_SIZE {
+        return Err(UnpackError::Empty);
+    }
+    let magic = u32::from_le_bytes(data[0..4].try_into().unwrap());
+    if magic != RECORD_MAGIC {
+        return Err(UnpackError::Empty);
+    }
+    let schema = u16::from_le_bytes(data[4..6].try_into().unwrap());
+    let len = u16::from_le_bytes(data[6..8].try_into().unwrap()) as usize;
+    let crc = u32::from_le_bytes(data[8..12].try_into().unwrap());
+    let body = data.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len).ok_or(UnpackError::Corrupt)?;
+    if crc32(body) != crc {
+        return Err(UnpackError::Corrupt);
+    }
+    if schema > T::SCHEMA {
+        return Err(UnpackError::Unsupported);
+    }
+    T::decode(&mut Decoder::new(body,schema)).ok_or(UnpackError::Corrupt)
+}
@@ -0,0 +1,144 @@
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use esp_println::println;
+use hal::macros::ram;
+use heapless::Vec;
+use crate::sleep::{clear_wakeup, get_rtc_ms, set_wakeup_rtc_ms, WakeupTask};
+use crate::storage::{ALARM_INFO, ALARM_MAX, NvsStorage};
+use crate::worldtime::{clock_ready, get_clock};
+
+pub const SNOOZE_SECS:u64 = 5 * 60;
+//检查间隔过长时(例如关机)只补响最近这段时间内错过的闹钟
+const MISSED_SECS:u64 = 120;
+const MAX_WAIT_SECS:u64 = 60;
+
+//上次检查的时间，保存在 rtc 中，唤醒后补响睡眠期间到点的闹钟
+#[ram(rtc_fast)]
+static mut ALARM_CHECKED_TIMESTAMP:u64 = 0;
+//贪睡后再次响铃的时间与闹钟 id，0 表示没有
+#[ram(rtc_fast)]
+static mut SNOOZE_TIMESTAMP:u64 = 0;
+#[ram(rtc_fast)]
+static mut SNOOZE_ID:u8 = 0;
+
+//到点的闹钟 id，同时到点的依次排队，主页面收到后暂停当前页面并覆盖显示响铃页面
+pub static ALARM_RING_CHANNEL: Channel<CriticalSectionRawMutex, u8, { ALARM_MAX + 1 }> = Channel::new();
+//闹钟修改、贪睡或关闭后通知重新计算下次响铃
+pub static ALARM_CHANGED_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+pub async fn snooze(id:u8){
+    if let Some(clock) = get_clock() {
+        unsafe {
+            SNOOZE_TIMESTAMP = clock.now().await.unix_timestamp() as u64 + SNOOZE_SECS;
+            SNOOZE_ID = id;
+        }
+    }
+    ALARM_CHANGED_SIGNAL.signal(());
+}
+
+//闹钟删除后取消它的贪睡
+pub fn cancel_snooze(id:u8){
+    unsafe {
+        if SNOOZE_ID == id {
+            SNOOZE_TIMESTAMP = 0;
+            SNOOZE_ID = 0;
+        }
+    }
+}
+
+pub async fn dismiss(){
+    unsafe {
+        SNOOZE_TIMESTAMP = 0;
+    }
+    ALARM_CHANGED_SIGNAL.signal(());
+}
+
+//from 之后到 to 为止到点的所有闹钟，包含贪睡，单次闹钟响过后关闭
+async fn due_alarms(from:u64,to:u64)->Vec<u8,{ ALARM_MAX + 1 }>{
+    let mut result = Vec::new();
+    unsafe {
+        if SNOOZE_TIMESTAMP > 0 && SNOOZE_TIMESTAMP <= to {
+            SNOOZE_TIMESTAMP = 0;
+            let _ = result.push(SNOOZE_ID);
+        }
+    }
+    let mut alarm_info = ALARM_INFO.lock().await;
+    let Some(storage) = alarm_info.as_mut() else { return result; };
+    let mut changed = false;
+    for alarm in storage.alarms.iter_mut() {
+        if !alarm.next_trigger(from).map_or(false,|t| t <= to) {
+            continue;
+        }
+        if alarm.once() {
+            alarm.enabled = false;
+            changed = true;
+        }
+        if !result.contains(&alarm.id) {
+            let _ = result.push(alarm.id);
+        }
+    }
+    if changed {
+        if let Err(e) = storage.write() {
+            println!("save alarm fail：{:?}",e);
+        }
+    }
+    result
+}
+
+//after 之后最近一次响铃时间，包含贪睡
+async fn next_timestamp(after:u64)->Option<u64>{
+    let snooze = unsafe{ SNOOZE_TIMESTAMP };
+    let snooze = if snooze > after { Some(snooze) } else { None };
+    let next = ALARM_INFO.lock().await.as_ref()
+        .and_then(|v| v.alarms.iter().filter_map(|a| a.next_trigger(after)).min());
+    match (snooze,next) {
+        (Some(a),Some(b)) => Some(a.min(b)),
+        (a,b) => a.or(b),
+    }
+}
+
+#[embassy_executor::task]
+pub async fn alarm_worker() {
+    loop {
+        //时间未同步或唤醒后未恢复时无法判断
+        if !clock_ready().await {
+            Timer::after(Duration::from_secs(1)).await;
+            continue;
+        }
+        let now = get_clock().unwrap().now().await.unix_timestamp() as u64;
+        let checked = unsafe{ ALARM_CHECKED_TIMESTAMP };
+        let from = if checked == 0 || checked > now {
+            now
+        }else{
+            checked.max(now.saturating_sub(MISSED_SECS))
+        };
+        unsafe {
+            ALARM_CHECKED_TIMESTAMP = now;
+        }
+
+        for id in due_alarms(from,now).await {
+            println!("alarm ring:{}",id);
+            if ALARM_RING_CHANNEL.try_send(id).is_err() {
+                println!("alarm queue full:{}",id);
+            }
+        }
+
+        //深度睡眠时由定时器在下次响铃时唤醒
+        let wait_secs = match next_timestamp(now).await {
+            Some(next) => {
+                set_wakeup_rtc_ms(WakeupTask::Alarm, get_rtc_ms().await + (next - now) * 1000);
+                (next - now).min(MAX_WAIT_SECS)
+            }
+            None => {
+                clear_wakeup(WakeupTask::Alarm);
+                MAX_WAIT_SECS
+            }
+        };
+
+        select(Timer::after(Duration::from_secs(wait_secs)),ALARM_CHANGED_SIGNAL.wait()).await;
+        ALARM_CHANGED_SIGNAL.reset();
+    }
+}
@@ -81,6 +81,18 @@ pub async fn clear(){
     vec.clear();
 }
 
+//覆盖页面(例如闹钟响铃)显示前暂存当前页面的事件，关闭后恢复
+static SUSPENDED:Mutex<CriticalSectionRawMutex,Vec<Listener,20>>  = Mutex::new(Vec::new()) ;
+pub async fn suspend(){
+    let mut vec = LISTENER.lock().await;
+    *SUSPENDED.lock().await = core::mem::take(&mut *vec);
+}
+
+pub async fn resume(){
+    let mut vec = LISTENER.lock().await;
+    *vec = core::mem::take(&mut *SUSPENDED.lock().await);
+}
+
 
 pub async fn toggle_event(event_type: EventType,ms:u64){
     println!("event_type:{:?}",event_type);
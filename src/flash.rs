@@ -0,0 +1,25 @@
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use esp_storage::{FlashStorage, FlashStorageError};
+
+//flash 只能按扇区擦除，擦除后为 0xFF，写入只能把 1 变成 0
+pub const SECTOR_SIZE:usize = 4096;
+
+pub fn read_flash(flash_addr:u32, bytes: &mut [u8]) -> Result<(), FlashStorageError> {
+    let mut flash = FlashStorage::new();
+    flash.read(flash_addr,bytes)
+}
+
+//写入已擦除的区域，不会擦除，addr 与长度按 4 字节对齐
+pub fn write_flash(flash_addr:u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
+    let mut flash = FlashStorage::new();
+    flash.write(flash_addr, bytes)
+}
+
+//擦除 from 到 to 之间的扇区，两端都要按扇区对齐
+pub fn erase_flash(from:u32, to:u32) -> Result<(), FlashStorageError> {
+    if from as usize % SECTOR_SIZE != 0 || to as usize % SECTOR_SIZE != 0 {
+        return Err(FlashStorageError::NotAligned);
+    }
+    let mut flash = FlashStorage::new();
+    flash.erase(from, to)
+}
@@ -0,0 +1,110 @@
+use alloc::format;
+use core::str::FromStr;
+use embassy_futures::select::select;
+use embassy_time::{Duration, Timer};
+use esp_println::println;
+use heapless::String;
+use reqwless::response::Status;
+use crate::request::{RequestClient, RequestError};
+use crate::storage::{OTHER_INFO, TIMER_LOG_SYNC_SIGNAL, TIMER_LOG_STATE, WORK_ITEM_INFO};
+use crate::wifi::use_wifi;
+
+const MIN_RETRY_SECS:u64 = 5;
+const MAX_RETRY_SECS:u64 = 600;
+const IDLE_SECS:u64 = 3600;
+
+#[derive(Debug)]
+enum SyncError{
+    NoNetwork,
+    Request(RequestError),
+}
+
+//读取上传配置，未配置地址时返回 None
+async fn sync_config()->Option<(String<128>,String<64>)>{
+    if let Some(other) = OTHER_INFO.lock().await.as_ref() {
+        if !other.sync_url.is_empty() {
+            return Some((other.sync_url.clone(),other.token.clone()));
+        }
+    }
+    None
+}
+
+//服务器拒绝了这条记录，重试也不会成功，跳过它继续上传后面的记录
+//认证失败、地址错误和限流是配置或服务器的问题，仍然退避重试
+fn is_rejected(e:&RequestError)->bool{
+    match e {
+        RequestError::HttpStatus(status) => status.is_client_error()
+            && !matches!(status, Status::Unauthorized | Status::Forbidden | Status::NotFound | Status::TooManyRequests),
+        _ => false,
+    }
+}
+
+//逐条上传待同步记录，服务器返回 2xx 后才标记为已同步，被拒绝的记录也标记，避免一直卡在这条
+async fn sync_logs(url:&str,token:&str)->Result<usize,SyncError>{
+    let pending = match TIMER_LOG_STATE.lock().await.as_ref() {
+        Some(state) => state.pending_logs(),
+        None => return Ok(0),
+    };
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let lease = use_wifi().await.map_err(|_| SyncError::NoNetwork)?;
+    let mut request = RequestClient::new(lease.stack()).await;
+    let authorization = format!("Bearer {}",token);
+    let headers = [("Authorization",authorization.as_str())];
+
+    let mut synced = 0;
+    for index in pending {
+        let log = match TIMER_LOG_STATE.lock().await.as_ref() {
+            Some(state) => state.read_log(index),
+            None => None,
+        };
+        let Some(log) = log else { continue; };
+
+        let work_name:String<20> = match WORK_ITEM_INFO.lock().await.as_ref() {
+            Some(work_items) => String::from_str(work_items.name(log.work_type).unwrap_or("")).unwrap_or_default(),
+            None => String::new(),
+        };
+        let body = log.to_json(work_name.as_str());
+        match request.send_post_json(url, body.as_bytes(), &headers).await {
+            Ok(_) => {}
+            Err(e) if is_rejected(&e) => {
+                println!("timer log rejected:{} {:?}",index,e);
+            }
+            Err(e) => return Err(SyncError::Request(e)),
+        }
+        if let Some(state) = TIMER_LOG_STATE.lock().await.as_mut() {
+            if let Err(e) = state.mark_synced(index) {
+                println!("mark synced fail：{:?}",e);
+            }
+        }
+        synced += 1;
+    }
+    Ok(synced)
+}
+
+#[embassy_executor::task]
+pub async fn log_sync_worker() {
+    let mut retry_sec = MIN_RETRY_SECS;
+    loop {
+        let mut sleep_sec = IDLE_SECS;
+        if let Some((url,token)) = sync_config().await {
+            match sync_logs(url.as_str(),token.as_str()).await {
+                Ok(n) => {
+                    println!("timer log synced:{}",n);
+                    retry_sec = MIN_RETRY_SECS;
+                }
+                Err(e) => {
+                    //离线或服务器异常，退避重试
+                    println!("timer log sync fail:{:?}",e);
+                    sleep_sec = retry_sec;
+                    retry_sec = (retry_sec * 2).min(MAX_RETRY_SECS);
+                }
+            }
+        }
+
+        select(Timer::after(Duration::from_secs(sleep_sec)),TIMER_LOG_SYNC_SIGNAL.wait()).await;
+        TIMER_LOG_SYNC_SIGNAL.reset();
+    }
+}
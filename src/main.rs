@@ -12,6 +12,7 @@ mod display;
 mod wifi;
 mod random;
 mod storage;
+mod flash;
 mod ec11;
 mod event;
 mod sound;
@@ -21,12 +22,16 @@ mod sleep;
 mod model;
 mod request;
 mod weather;
+mod log_sync;
+mod alarm;
 mod worldtime;
 mod web_service;
 mod chip8;
 mod widgets;
 mod pages;
 
+use work_timer_core::{dhcp, http, interval, kv, mdns, record};
+
 use alloc::format;
 use alloc::string::ToString;
 use core::convert::Infallible;
@@ -45,7 +50,7 @@ use hal::analog::adc::{Adc, AdcConfig, Attenuation};
 use hal::gpio::{Gpio0, Gpio1, Gpio5, Io, Level, Output};
 use hal::peripheral::Peripheral;
 use hal::peripherals::{ADC1, Peripherals};
-use hal::reset::{get_reset_reason, get_wakeup_cause};
+use hal::reset::{get_reset_reason, get_wakeup_cause, software_reset};
 use hal::rtc_cntl::{Rtc, SocResetReason};
 use hal::spi::master::Spi;
 
@@ -63,9 +68,11 @@ use crate::pages::init_page::InitPage;
 use crate::pages::Page;
 use crate::sleep::{add_rtcio, refresh_active_time, RTC_MANGE};
 use crate::sound::{buzzer_task, PWM_PLAYER, PwmPlayer};
-use crate::storage::{enter_process, init_storage_area, WIFI_INFO};
+use crate::storage::{enter_process, init_storage_area, NvsStorage, WIFI_INFO};
 use crate::weather::weather_worker;
-use crate::wifi::{connect_wifi, start_wifi_ap, WIFI_MODEL, WifiModel};
+use crate::log_sync::log_sync_worker;
+use crate::alarm::alarm_worker;
+use crate::wifi::{connect_wifi, start_wifi_ap, take_ap_fallback, WIFI_MODEL, WifiModel};
 use crate::worldtime::{get_clock, ntp_worker};
 
 const DESCRIPTORS_SIZE: usize = 8 * 3;
@@ -204,21 +211,18 @@ async fn main_fallible(spawner: &Spawner)->Result<(),Error> {
 
     println!("start wifi");
 
-/*    loop {
-        println!("entry need_ap 1");
-        if let Some(wifi) = WIFI_INFO.lock().await.as_ref(){
-            println!("wifi_finish:{:?}",wifi.wifi_finish);
-            println!("wifi_ssid:{:?}",wifi.wifi_ssid);
-            //println!("wifi_password:{:?}",wifi.wifi_password);
-            if !wifi.wifi_finish {
-                need_ap = true;
-            }
-            println!("entry need_ap 2");
-            break;
+    //没有保存 wifi 信息时进入配网模式
+    if let Some(wifi) = WIFI_INFO.lock().await.as_ref(){
+        println!("wifi_finish:{:?}",wifi.wifi_finish);
+        println!("wifi_networks:{}",wifi.networks.len());
+        if !wifi.wifi_finish || wifi.networks.is_empty() {
+            need_ap = true;
         }
-        println!("entry need_ap");
-        Timer::after(Duration::from_millis(50)).await;
-    }*/
+    }
+    //上次启动多次连接失败，本次进入配网模式，配网模式下会定时重试已保存的网络
+    if take_ap_fallback() {
+        need_ap = true;
+    }
 
     if  need_ap {
         println!("entry ap");
@@ -235,7 +239,17 @@ async fn main_fallible(spawner: &Spawner)->Result<(),Error> {
             let mut qrcode_page = pages::setting_page::SettingPage::new();
             qrcode_page.bind_event().await;
             qrcode_page.run(spawner.clone()).await;
-            Timer::after(Duration::from_secs(50)).await;
+            //退出配网页面时，有保存的网络就重启再次尝试连接
+            if let Some(wifi) = WIFI_INFO.lock().await.as_mut() {
+                if !wifi.networks.is_empty() {
+                    wifi.wifi_finish = true;
+                    match wifi.write() {
+                        Ok(_) => software_reset(),
+                        Err(e) => println!("保存失败：{:?}",e),
+                    }
+                }
+            }
+            Timer::after(Duration::from_secs(1)).await;
         }
 
     }else {
@@ -245,6 +259,10 @@ async fn main_fallible(spawner: &Spawner)->Result<(),Error> {
         WIFI_MODEL.lock().await.replace(WifiModel::STA);
         spawner.spawn(weather_worker()).ok();
 
+        spawner.spawn(log_sync_worker()).ok();
+
+        spawner.spawn(alarm_worker()).ok();
+
         spawner.spawn(ntp_worker()).ok();
 
         spawner.spawn(pages::main_task(spawner.clone())).ok();
@@ -0,0 +1,74 @@
+use heapless::String;
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+//铃声数量，对应 SoundType::Music 的编号
+pub const ALARM_SOUNDS:u8 = 2;
+//编辑页可选的名称，另外还有不重复的默认名称 闹钟N
+pub const ALARM_LABELS:[&str;7] = ["起床","上班","午休","会议","运动","吃药","睡觉"];
+
+const WEEKDAY_TITLES:[&str;7] = ["一","二","三","四","五","六","日"];
+
+#[derive(Debug,Default,Clone)]
+pub struct Alarm{
+    pub id:u8, //删除其他闹钟后不变，贪睡和响铃按它查找，0 表示未分配
+    pub enabled:bool,
+    pub hour:u8,
+    pub minute:u8,
+    pub weekdays:u8, //bit0 为周一，bit6 为周日，0 表示只响一次
+    pub sound:u8,
+    pub label:String<20>,
+}
+
+impl Alarm {
+    pub fn is_valid(&self)->bool{
+        self.hour < 24 && self.minute < 60 && self.weekdays < 0x80 && self.sound < ALARM_SOUNDS
+            && self.label.len() <= self.label.capacity()
+            && core::str::from_utf8(self.label.as_bytes()).is_ok()
+    }
+
+    pub fn once(&self)->bool{
+        self.weekdays == 0
+    }
+
+    //after 之后第一次响铃的时间戳，按东八区计算
+    pub fn next_trigger(&self,after:u64)->Option<u64>{
+        if !self.enabled {
+            return None;
+        }
+        let offset = UtcOffset::from_hms(8,0,0).unwrap();
+        let after_local = OffsetDateTime::from_unix_timestamp(after as i64).ok()?.to_offset(offset);
+        for day in 0..8 {
+            let date = after_local.date() + Duration::days(day);
+            let trigger = date.with_hms(self.hour,self.minute,0).ok()?.assume_offset(offset).unix_timestamp() as u64;
+            if trigger <= after {
+                continue;
+            }
+            if self.once() || self.weekdays & (1 << date.weekday().number_days_from_monday()) != 0 {
+                return Some(trigger);
+            }
+        }
+        None
+    }
+
+    //重复规则的显示文字
+    pub fn weekdays_title(&self)->String<32>{
+        let mut title = String::new();
+        if self.once() {
+            let _ = title.push_str("单次");
+        }else if self.weekdays == 0x7F {
+            let _ = title.push_str("每天");
+        }else{
+            let _ = title.push_str("周");
+            for (index,day) in WEEKDAY_TITLES.iter().enumerate() {
+                if self.weekdays & (1 << index) != 0 {
+                    let _ = title.push_str(day);
+                }
+            }
+        }
+        title
+    }
+
+    pub fn weekday_title(index:usize)->&'static str{
+        WEEKDAY_TITLES[index % 7]
+    }
+}
@@ -1,2 +1,3 @@
 pub mod seniverse;
-pub mod timer_log;
\ No newline at end of file
+pub mod timer_log;
+pub mod alarm;
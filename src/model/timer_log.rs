@@ -1,13 +1,19 @@
-#[derive(Debug, Default)]
-enum FinishType {
+use alloc::format;
+use alloc::string::String;
+use crate::http::json_string;
+
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum FinishType {
     #[default]
     Success,
     Fail,
     Abort,
 }
 
-#[derive(Debug, Default)]
-enum WorkItem{
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum WorkItem{
 
     #[default]
     Learn = 1,
@@ -20,12 +26,60 @@ enum WorkItem{
 
 }
 
-#[derive(Debug,Default)]
+impl WorkItem {
+    //默认类别，首次启动时写入存储，之后可在网页中修改
+    pub const ALL: [WorkItem; 7] = [
+        WorkItem::Learn,
+        WorkItem::Eat,
+        WorkItem::Write,
+        WorkItem::Read,
+        WorkItem::WatchTv,
+        WorkItem::PlayGame,
+        WorkItem::UsePhone,
+    ];
+
+    pub fn title(&self)->&'static str{
+        match self {
+            WorkItem::Learn => "学习",
+            WorkItem::Eat => "吃饭",
+            WorkItem::Write => "写作",
+            WorkItem::Read => "阅读",
+            WorkItem::WatchTv => "看电视",
+            WorkItem::PlayGame => "玩游戏",
+            WorkItem::UsePhone => "玩手机",
+        }
+    }
+}
+
+#[derive(Debug,Default,Clone)]
 pub struct TimerLog{
-    is_sync:bool,
-    finish_type:FinishType,
-    begin_timestamp:u64,
-    end_timestamp:u64,
-    interval:u64,
-    work_type:WorkItem
-}
\ No newline at end of file
+    pub is_sync:bool,
+    pub finish_type:FinishType,
+    pub begin_timestamp:u64,//没有同步过时间时开始和结束时间都为 0
+    pub end_timestamp:u64,
+    pub interval:u64,
+    pub work_type:u8, //类别 id，对应类别列表中 id 相同的类别，0 为未分类
+    pub paused_secs:u32,
+    pub pause_count:u16,
+}
+
+impl TimerLog {
+    pub fn new(finish_type:FinishType,begin_timestamp:u64,end_timestamp:u64,interval:u64,work_type:u8,paused_secs:u32,pause_count:u16)->Self{
+        Self{
+            is_sync: false,
+            finish_type,
+            begin_timestamp,
+            end_timestamp,
+            interval,
+            work_type,
+            paused_secs,
+            pause_count,
+        }
+    }
+
+    //上传到服务器的 json 格式
+    pub fn to_json(&self,work_name:&str)->String{
+        format!("{{\"begin_timestamp\":{},\"end_timestamp\":{},\"interval\":{},\"finish_type\":\"{:?}\",\"work_type\":{},\"work_name\":{},\"paused_secs\":{},\"pause_count\":{}}}",
+                self.begin_timestamp,self.end_timestamp,self.interval,self.finish_type,self.work_type,json_string(work_name),self.paused_secs,self.pause_count)
+    }
+}
@@ -0,0 +1,277 @@
+use alloc::boxed::Box;
+use alloc::format;
+use core::fmt::Write;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::prelude::{Dimensions, DrawTarget};
+use esp_println::println;
+use heapless::{String, Vec};
+use lcd_drivers::color::TwoBitColor;
+
+use crate::alarm::{ALARM_CHANGED_SIGNAL, cancel_snooze};
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::model::alarm::{Alarm, ALARM_SOUNDS};
+use crate::pages::Page;
+use crate::storage::{ALARM_INFO, ALARM_MAX, AlarmStorage, NvsStorage};
+use crate::widgets::list_widget::{list_item_text, ListWidget};
+
+//编辑页的字段：开关、时、分、周一到周日、铃声、名称、删除
+const FIELD_ENABLED:usize = 0;
+const FIELD_HOUR:usize = 1;
+const FIELD_MINUTE:usize = 2;
+const FIELD_WEEKDAY:usize = 3;
+const FIELD_SOUND:usize = 10;
+const FIELD_LABEL:usize = 11;
+const FIELD_DELETE:usize = 12;
+const FIELD_COUNT:usize = 13;
+
+///闹钟列表与编辑，旋钮选择，按键 1/2 调整数值，按键 3 进入编辑或切换，按键 5 返回并保存
+pub struct AlarmPage{
+    running:bool,
+    need_render:bool,
+    storage:AlarmStorage,
+    choose_index:usize,
+    editing:Option<usize>,
+    field:usize,
+}
+
+impl AlarmPage {
+
+    fn list_items(&self)->Vec<String<20>,20>{
+        let mut items = Vec::new();
+        match self.editing {
+            None => {
+                for alarm in self.storage.alarms.iter() {
+                    let text = format!("{:02}:{:02} {} {}",alarm.hour,alarm.minute
+                                       ,if alarm.enabled { "开" } else { "关" },alarm.weekdays_title());
+                    let _ = items.push(list_item_text(text.as_str()));
+                }
+                if self.storage.alarms.len() < ALARM_MAX {
+                    let _ = items.push(list_item_text("+ 新增"));
+                }
+            }
+            Some(index) => {
+                let alarm = &self.storage.alarms[index];
+                for field in 0..FIELD_COUNT {
+                    let mut text:String<40> = String::new();
+                    let _ = match field {
+                        FIELD_ENABLED => write!(text,"开关：{}",if alarm.enabled { "开" } else { "关" }),
+                        FIELD_HOUR => write!(text,"时：{:02}",alarm.hour),
+                        FIELD_MINUTE => write!(text,"分：{:02}",alarm.minute),
+                        FIELD_SOUND => write!(text,"铃声：{}",alarm.sound + 1),
+                        FIELD_LABEL => write!(text,"名称：{}",alarm.label),
+                        FIELD_DELETE => write!(text,"删除"),
+                        _ => {
+                            let day = field - FIELD_WEEKDAY;
+                            write!(text,"周{}：{}",Alarm::weekday_title(day)
+                                   ,if alarm.weekdays & (1 << day) != 0 { "响" } else { "-" })
+                        }
+                    };
+                    let _ = items.push(list_item_text(text.as_str()));
+                }
+            }
+        }
+        items
+    }
+
+    fn increase(&mut self){
+        if self.editing.is_none() {
+            let max = self.list_items().len();
+            if self.choose_index + 1 < max {
+                self.choose_index += 1;
+                self.need_render = true;
+            }
+        }else if self.field + 1 < FIELD_COUNT {
+            self.field += 1;
+            self.need_render = true;
+        }
+    }
+
+    fn decrease(&mut self){
+        if self.editing.is_none() {
+            if self.choose_index > 0 {
+                self.choose_index -= 1;
+                self.need_render = true;
+            }
+        }else if self.field > 0 {
+            self.field -= 1;
+            self.need_render = true;
+        }
+    }
+
+    //调整当前字段，step 为 1 或 -1
+    fn change(&mut self,step:i32){
+        let Some(index) = self.editing else { return; };
+        if self.field == FIELD_LABEL {
+            self.storage.change_label(index, step);
+            self.need_render = true;
+            return;
+        }
+        let alarm = &mut self.storage.alarms[index];
+        match self.field {
+            FIELD_ENABLED => alarm.enabled = !alarm.enabled,
+            FIELD_HOUR => alarm.hour = (alarm.hour as i32 + step).rem_euclid(24) as u8,
+            FIELD_MINUTE => alarm.minute = (alarm.minute as i32 + step).rem_euclid(60) as u8,
+            FIELD_SOUND => alarm.sound = (alarm.sound as i32 + step).rem_euclid(ALARM_SOUNDS as i32) as u8,
+            FIELD_LABEL | FIELD_DELETE => return,
+            field => alarm.weekdays ^= 1 << (field - FIELD_WEEKDAY),
+        }
+        self.need_render = true;
+    }
+
+    async fn confirm(&mut self){
+        match self.editing {
+            None => {
+                if self.choose_index >= self.storage.alarms.len() {
+                    match self.storage.add() {
+                        Some(index) => self.choose_index = index,
+                        None => return,
+                    }
+                }
+                self.editing = Some(self.choose_index);
+                self.field = FIELD_ENABLED;
+            }
+            Some(index) => {
+                if self.field == FIELD_DELETE {
+                    let alarm = self.storage.alarms.remove(index);
+                    cancel_snooze(alarm.id);
+                    self.editing = None;
+                    self.choose_index = index.min(self.storage.alarms.len());
+                    self.save().await;
+                }else{
+                    self.change(1);
+                }
+            }
+        }
+        self.need_render = true;
+    }
+
+    async fn save(&mut self){
+        let storage = self.storage.clone();
+        match storage.write() {
+            Ok(_) => { println!("alarm saved:{}",self.storage.alarms.len()); }
+            Err(e) => { println!("save alarm fail：{:?}",e); }
+        }
+        ALARM_INFO.lock().await.replace(storage);
+        ALARM_CHANGED_SIGNAL.signal(());
+    }
+
+    async fn back(&mut self){
+        if let Some(index) = self.editing {
+            self.editing = None;
+            self.choose_index = index;
+            self.need_render = true;
+            self.save().await;
+            return;
+        }
+        self.running = false;
+    }
+}
+
+impl Page for AlarmPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            storage: AlarmStorage::default(),
+            choose_index: 0,
+            editing: None,
+            field: FIELD_ENABLED,
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+                let items = self.list_items();
+                let labels:Vec<&str,20> = items.iter().map(|v| v.as_str()).collect();
+                let mut list_widget = ListWidget::new(Point::new(0, 0)
+                                                      , TwoBitColor::Black
+                                                      , TwoBitColor::White
+                                                      , display.bounding_box().size
+                                                      , labels
+                );
+                list_widget.choose(if self.editing.is_some() { self.field } else { self.choose_index });
+                let _ = list_widget.draw(display);
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        if let Some(storage) = ALARM_INFO.lock().await.as_ref() {
+            self.storage = storage.clone();
+        }
+        loop {
+            if !self.running {
+                break;
+            }
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        event::on_target(EventType::WheelFront,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.increase();
+            });
+        }).await;
+        event::on_target(EventType::WheelBack,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.decrease();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(1),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(1);
+            });
+        }).await;
+        event::on_target(EventType::KeyLongIng(1),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(1);
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(2),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(-1);
+            });
+        }).await;
+        event::on_target(EventType::KeyLongIng(2),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(-1);
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(3),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.confirm().await;
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(5),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.back().await;
+            });
+        }).await;
+    }
+}
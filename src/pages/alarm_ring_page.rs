@@ -0,0 +1,153 @@
+use alloc::boxed::Box;
+use alloc::format;
+use eg_seven_segment::SevenSegmentStyleBuilder;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::prelude::{DrawTarget, OriginDimensions};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use lcd_drivers::color::TwoBitColor;
+use u8g2_fonts::{FontRenderer, U8g2TextStyle};
+use u8g2_fonts::fonts;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use crate::alarm::{dismiss, snooze};
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::model::alarm::Alarm;
+use crate::pages::Page;
+use crate::sleep::refresh_active_time;
+use crate::sound::{player_buzzer, SoundType, stop_buzzer};
+use crate::storage::ALARM_INFO;
+
+//无人处理时自动贪睡
+const RING_SECS:u64 = 3 * 60;
+
+///闹钟响铃，短按贪睡，长按关闭
+pub struct AlarmRingPage{
+    running:bool,
+    need_render:bool,
+    id:u8,
+    alarm:Alarm,
+}
+
+impl AlarmRingPage {
+
+    pub async fn set_alarm(&mut self,id:u8){
+        self.id = id;
+        if let Some(alarm) = ALARM_INFO.lock().await.as_ref().and_then(|v| v.get(id)) {
+            self.alarm = alarm.clone();
+        }
+        self.need_render = true;
+    }
+
+    async fn snooze(&mut self){
+        if !self.running {
+            return;
+        }
+        stop_buzzer().await;
+        snooze(self.id).await;
+        self.running = false;
+    }
+
+    async fn dismiss(&mut self){
+        if !self.running {
+            return;
+        }
+        stop_buzzer().await;
+        dismiss().await;
+        self.running = false;
+    }
+}
+
+impl Page for AlarmRingPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            id: 0,
+            alarm: Alarm::default(),
+        }
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+
+                let font = FontRenderer::new::<fonts::u8g2_font_wqy16_t_gb2312>();
+                let _ = font.render_aligned(
+                    self.alarm.label.as_str(),
+                    Point::new(display.size().width as i32 / 2, 10),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::Transparent(TwoBitColor::Black),
+                    display,
+                );
+
+                let character_style = SevenSegmentStyleBuilder::new()
+                    .digit_size(Size::new(30, 60))
+                    .segment_width(5)
+                    .segment_color(TwoBitColor::Black)
+                    .build();
+                let text_style = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                let time = format!("{:02}:{:02}",self.alarm.hour,self.alarm.minute);
+                let _ = Text::with_text_style(
+                    time.as_str(),
+                    Point::new(display.size().width as i32 / 2, display.size().height as i32 / 2),
+                    character_style,
+                    text_style,
+                ).draw(display);
+
+                let style =
+                    U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                let _ = Text::new("短按稍后提醒，长按关闭", Point::new(0, display.size().height as i32 - 4), style).draw(display);
+
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        player_buzzer(SoundType::Music(self.alarm.sound as u32)).await;
+        let begin_time = Instant::now().as_secs();
+        loop {
+            if !self.running {
+                break;
+            }
+            if Instant::now().as_secs() - begin_time > RING_SECS {
+                self.snooze().await;
+                break;
+            }
+            refresh_active_time().await;
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        for key in [1,2,3,5] {
+            event::on_target(EventType::KeyShort(key),Self::mut_to_ptr(self),  move |info|  {
+                return Box::pin(async move {
+                    let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                    mut_ref.snooze().await;
+                });
+            }).await;
+            event::on_target(EventType::KeyLongStart(key),Self::mut_to_ptr(self),  move |info|  {
+                return Box::pin(async move {
+                    let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                    mut_ref.dismiss().await;
+                });
+            }).await;
+        }
+    }
+}
@@ -45,6 +45,10 @@ impl Page for CalendarPage {
         }
     }
 
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
     async fn render(&mut self)  {
         if self.need_render {
             self.need_render = false;
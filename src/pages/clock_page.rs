@@ -29,7 +29,7 @@ use crate::pages::{ Page};
 use crate::pages::main_page::MainPage;
 use crate::request::{RequestClient, ResponseData};
 use crate::widgets::clock_widget::ClockWidget;
-use crate::wifi::{finish_wifi, use_wifi};
+use crate::wifi::use_wifi;
 use crate::worldtime::{get_clock, sync_time_success};
 
 pub struct ClockPage {
@@ -68,13 +68,13 @@ impl ClockPage {
         let stack = use_wifi().await;
         if let Ok(v) = stack {
             println!("请求 stack 成功");
-            let mut request = RequestClient::new(v).await;
+            let mut request = RequestClient::new(v.stack()).await;
             println!("开始请求成功");
             //let result = request.send_request("https://worldtimeapi.org/api/timezone/Europe/Copenhagen.txt").await;
             let result = request.send_request("http://api.seniverse.com/v3/weather/daily.json?key=SvRIiZPU5oGiqcHc1&location=beijing&language=en&unit=c&start=0&days=5").await;
+            drop(v);
             match result {
                 Ok(response) => {
-                    finish_wifi().await;
                     self.loading = false;
                     self.error = None;
                     let daily_result = form_json(&response.data[..response.length]);
@@ -82,7 +82,6 @@ impl ClockPage {
                     println!("请求成功{}", core::str::from_utf8(& response.data[..response.length]).unwrap());
                 }
                 Err(e) => {
-                    finish_wifi().await;
                     self.loading = false;
                     self.error = Some("请求失败".to_string());
                     println!("请求失败{:?}",e);
@@ -99,13 +98,11 @@ impl ClockPage {
     async fn sync_time(&mut self) {
         let stack = use_wifi().await;
         if let Ok(v) = stack {
-            let sleep_sec = match crate::worldtime::ntp_request(v, get_clock().unwrap()).await {
+            let sleep_sec = match crate::worldtime::ntp_request(v.stack(), get_clock().unwrap()).await {
                 Err(_) => {
-                    finish_wifi().await;
                     println!("NTP error response");
                 }
                 Ok(_) => {
-                    finish_wifi().await;
                     println!("NTP ok ?");
                 },
             };
@@ -182,6 +179,10 @@ impl Page for ClockPage {
         }).await;
     }
 
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
     async fn render(&mut self)  {
         if self.need_render {
             self.need_render = false;
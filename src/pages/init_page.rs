@@ -16,7 +16,6 @@ use crate::widgets::calendar::Calendar;
 use crate::widgets::clock_widget::ClockWidget;
 use crate::worldtime::{CLOCK_SYNC_TIME_SECOND, get_clock};
 use u8g2_fonts::fonts;
-use crate::wifi::use_wifi;
 
 //用于调试显示
 pub struct InitPage{
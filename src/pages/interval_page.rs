@@ -0,0 +1,307 @@
+use alloc::boxed::Box;
+use alloc::format;
+use eg_seven_segment::SevenSegmentStyleBuilder;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, OriginDimensions};
+use embedded_graphics::text::{Alignment, Baseline, Text, TextStyleBuilder};
+use esp_println::println;
+use heapless::Vec;
+use lcd_drivers::color::TwoBitColor;
+use u8g2_fonts::{FontRenderer, U8g2TextStyle};
+use u8g2_fonts::fonts;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::interval::{IntervalCursor, IntervalSequence};
+use crate::pages::Page;
+use crate::sound::{player_buzzer, SoundType, stop_buzzer};
+use crate::storage::{INTERVAL_INFO, INTERVAL_MAX};
+use crate::widgets::list_widget::ListWidget;
+
+///间歇训练，先选择序列，按键 3 开始/暂停，按键 5 停止并返回列表
+pub struct IntervalPage{
+    running:bool,
+    need_render:bool,
+    sequences:Vec<IntervalSequence,INTERVAL_MAX>,
+    choose_index:usize,
+    selected:Option<usize>,
+    cursor:Option<IntervalCursor>,
+    step_end:Option<Instant>,//运行中时当前步骤结束的时间
+    remaining:Duration,//暂停时剩余的时间
+    finished:bool,
+    last_secs:u64,
+}
+
+impl IntervalPage {
+
+    fn sequence(&self)->Option<&IntervalSequence>{
+        self.sequences.get(self.selected?)
+    }
+
+    fn remaining_secs(&self)->u64{
+        let remaining = match self.step_end {
+            Some(step_end) => step_end.saturating_duration_since(Instant::now()),
+            None => self.remaining,
+        };
+        (remaining.as_millis() + 999) / 1000
+    }
+
+    //进入步骤时的提示音：重复组内每轮开始、组内切换、单独步骤各不相同
+    async fn step_sound(&self,cursor:IntervalCursor){
+        let Some(sequence) = self.sequence() else { return; };
+        if sequence.repeat(cursor) > 1 {
+            if cursor.step == 0 {
+                player_buzzer(SoundType::Tips(0)).await;
+            }else{
+                player_buzzer(SoundType::Tips(1)).await;
+            }
+        }else{
+            player_buzzer(SoundType::Tips(2)).await;
+        }
+    }
+
+    async fn start(&mut self,index:usize){
+        self.selected = Some(index);
+        self.finished = false;
+        let Some(sequence) = self.sequence() else { return; };
+        let Some(cursor) = sequence.first() else { return; };
+        let secs = sequence.step(cursor).map_or(0,|v| v.secs);
+        self.cursor = Some(cursor);
+        self.step_end = Some(Instant::now() + Duration::from_secs(secs as u64));
+        self.step_sound(cursor).await;
+        self.need_render = true;
+    }
+
+    //当前步骤结束，从上一步的结束时间接着算，避免累积误差
+    async fn next_step(&mut self){
+        let (Some(cursor),Some(step_end)) = (self.cursor,self.step_end) else { return; };
+        let Some(sequence) = self.sequence() else { return; };
+        match sequence.next(cursor) {
+            Some(next) => {
+                let secs = sequence.step(next).map_or(0,|v| v.secs);
+                self.cursor = Some(next);
+                self.step_end = Some(step_end + Duration::from_secs(secs as u64));
+                self.step_sound(next).await;
+            }
+            None => {
+                println!("interval finished");
+                self.step_end = None;
+                self.remaining = Duration::from_ticks(0);
+                self.finished = true;
+                player_buzzer(SoundType::Music(1)).await;
+            }
+        }
+        self.need_render = true;
+    }
+
+    async fn toggle_starting(&mut self){
+        if self.selected.is_none() {
+            if self.choose_index < self.sequences.len() {
+                self.start(self.choose_index).await;
+            }
+            return;
+        }
+        if self.finished {
+            stop_buzzer().await;
+            self.start(self.selected.unwrap()).await;
+            return;
+        }
+        match self.step_end.take() {
+            Some(step_end) => {
+                self.remaining = step_end.saturating_duration_since(Instant::now());
+            }
+            None => {
+                self.step_end = Some(Instant::now() + self.remaining);
+            }
+        }
+        self.need_render = true;
+    }
+
+    fn increase(&mut self){
+        if self.selected.is_none() && self.choose_index + 1 < self.sequences.len() {
+            self.choose_index += 1;
+            self.need_render = true;
+        }
+    }
+
+    fn decrease(&mut self){
+        if self.selected.is_none() && self.choose_index > 0 {
+            self.choose_index -= 1;
+            self.need_render = true;
+        }
+    }
+
+    async fn back(&mut self){
+        if self.selected.is_some() {
+            stop_buzzer().await;
+            self.selected = None;
+            self.cursor = None;
+            self.step_end = None;
+            self.finished = false;
+            self.need_render = true;
+            return;
+        }
+        self.running = false;
+    }
+}
+
+impl Page for IntervalPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            sequences: Vec::new(),
+            choose_index: 0,
+            selected: None,
+            cursor: None,
+            step_end: None,
+            remaining: Duration::from_ticks(0),
+            finished: false,
+            last_secs: 0,
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+                let style =
+                    U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+
+                let (Some(sequence),Some(cursor)) = (self.sequence(),self.cursor) else {
+                    if self.sequences.is_empty() {
+                        let _ = Text::new("请在网页中配置间歇训练", Point::new(0, 50), style).draw(display);
+                    }else{
+                        let items:Vec<&str,20> = self.sequences.iter().map(|v| v.name.as_str()).collect();
+                        let mut list_widget = ListWidget::new(Point::new(0, 0)
+                                                              , TwoBitColor::Black
+                                                              , TwoBitColor::White
+                                                              , display.bounding_box().size
+                                                              , items
+                        );
+                        list_widget.choose(self.choose_index);
+                        let _ = list_widget.draw(display);
+                    }
+                    RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                    return;
+                };
+
+                let width = display.size().width as i32;
+                let height = display.size().height as i32;
+
+                let repeat = sequence.repeat(cursor);
+                let title = if repeat > 1 {
+                    format!("{} {}/{}",sequence.name,cursor.round,repeat)
+                }else{
+                    format!("{}",sequence.name)
+                };
+                let _ = Text::new(title.as_str(), Point::new(0, 12), style.clone()).draw(display);
+                if self.step_end.is_none() && !self.finished {
+                    let _ = Text::new("暂停", Point::new(width - 24, 12), style.clone()).draw(display);
+                }
+
+                let step_name = if self.finished { "完成" } else { sequence.step(cursor).map_or("",|v| v.name.as_str()) };
+                let font = FontRenderer::new::<fonts::u8g2_font_wqy16_t_gb2312>();
+                let _ = font.render_aligned(
+                    step_name,
+                    Point::new(width / 2, 20),
+                    VerticalPosition::Top,
+                    HorizontalAlignment::Center,
+                    FontColor::Transparent(TwoBitColor::Black),
+                    display,
+                );
+
+                let secs = self.remaining_secs();
+                let time = format!("{:02}:{:02}",secs / 60,secs % 60);
+                let character_style = SevenSegmentStyleBuilder::new()
+                    .digit_size(Size::new(30, 60))
+                    .segment_width(5)
+                    .segment_color(TwoBitColor::Black)
+                    .build();
+                let text_style = TextStyleBuilder::new()
+                    .alignment(Alignment::Center)
+                    .baseline(Baseline::Middle)
+                    .build();
+                let _ = Text::with_text_style(
+                    time.as_str(),
+                    Point::new(width / 2, height / 2 + 8),
+                    character_style,
+                    text_style,
+                ).draw(display);
+
+                if !self.finished {
+                    let next = match sequence.next(cursor).and_then(|v| sequence.step(v)) {
+                        Some(step) => format!("下一步：{} {}秒",step.name,step.secs),
+                        None => format!("下一步：完成"),
+                    };
+                    let _ = Text::new(next.as_str(), Point::new(0, height - 4), style).draw(display);
+                }
+
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        if let Some(storage) = INTERVAL_INFO.lock().await.as_ref() {
+            self.sequences = storage.sequences.clone();
+        }
+        loop {
+            if !self.running {
+                break;
+            }
+            if let Some(step_end) = self.step_end {
+                if Instant::now() >= step_end {
+                    self.next_step().await;
+                }
+                let secs = self.remaining_secs();
+                if secs != self.last_secs {
+                    self.last_secs = secs;
+                    self.need_render = true;
+                }
+            }
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        event::on_target(EventType::KeyShort(3),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.toggle_starting().await;
+            });
+        }).await;
+        event::on_target(EventType::WheelFront,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.increase();
+            });
+        }).await;
+        event::on_target(EventType::WheelBack,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.decrease();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(5),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.back().await;
+            });
+        }).await;
+    }
+}
@@ -1,4 +1,5 @@
 use alloc::boxed::Box;
+use core::pin::pin;
 use heapless::String;
 use heapless::Vec;
 use core::str::FromStr;
@@ -18,15 +19,21 @@ use hal::macros::ram;
 use lcd_drivers::color::TwoBitColor;
 
 use crate::{ event};
+use crate::alarm::ALARM_RING_CHANNEL;
 use crate::display::{display_mut,  RENDER_CHANNEL, RenderInfo};
 use crate::event::EventType;
 use crate::pages::clock_page::{ClockPage};
 use crate::pages::{MenuItem, Page, PageEnum};
+use crate::pages::alarm_page::AlarmPage;
+use crate::pages::alarm_ring_page::AlarmRingPage;
 use crate::pages::calendar_page::CalendarPage;
 use crate::pages::games_page::GamesPage;
-use crate::pages::PageEnum::{ECalendarPage, EChip8Page, EClockPage, ESettingPage, ETimerPage, EWeatherPage};
+use crate::pages::interval_page::IntervalPage;
+use crate::pages::network_page::NetworkPage;
+use crate::pages::PageEnum::{EAlarmPage, ECalendarPage, EChip8Page, EClockPage, EIntervalPage, ENetworkPage, ESettingPage, EStatsPage, ETimerPage, EWeatherPage};
 use crate::pages::setting_page::{SettingPage};
-use crate::pages::timer_page::TimerPage;
+use crate::pages::stats_page::StatsPage;
+use crate::pages::timer_page::{has_rtc_countdown, TimerPage};
 use crate::pages::weather_page::WeatherPage;
 use crate::widgets::list_widget::ListWidget;
 
@@ -51,6 +58,13 @@ impl MainPage {
         let mut page_index = unsafe{ PAGE_INDEX };
 
         MAIN_PAGE.lock().await.replace(MainPage::new());
+        //倒计时未结束时被唤醒，直接进入定时器页面
+        if has_rtc_countdown() {
+            if let Some(index) = MAIN_PAGE.lock().await.as_ref().unwrap().menus.as_ref().unwrap()
+                .iter().position(|v| matches!(v.page_enum, ETimerPage)) {
+                page_index = index as u32;
+            }
+        }
         spawner.spawn(increase()).ok();
         spawner.spawn(decrease()).ok();
         Self::bind_event(MAIN_PAGE.lock().await.as_mut().unwrap()).await;
@@ -85,6 +99,34 @@ impl MainPage {
         self.need_render = true;
         Self::bind_event(self).await;
     }
+
+    //运行页面，闹钟响铃时覆盖显示响铃页面，页面不会被 drop，只是暂停运行，响铃结束后恢复事件并重绘
+    async fn run_page<P:Page>(page:&mut P,spawner: Spawner){
+        page.bind_event().await;
+        let ptr = P::mut_to_ptr(page);
+        let mut run = pin!(page.run(spawner));
+        loop {
+            match select(run.as_mut(), ALARM_RING_CHANNEL.receive()).await {
+                Either::First(_) => return,
+                Either::Second(id) => {
+                    Self::ring_alarm(id, spawner).await;
+                    let page:Option<&mut P> = P::mut_by_ptr(Some(ptr));
+                    if let Some(page) = page {
+                        page.redraw();
+                    }
+                }
+            }
+        }
+    }
+
+    async fn ring_alarm(id:u8,spawner: Spawner){
+        event::suspend().await;
+        let mut ring_page = AlarmRingPage::new();
+        ring_page.set_alarm(id).await;
+        ring_page.bind_event().await;
+        ring_page.run(spawner).await;
+        event::resume().await;
+    }
 }
 impl Page for  MainPage{
 
@@ -96,6 +138,10 @@ impl Page for  MainPage{
         menus.push(MenuItem::new(String::<20>::from_str("天气").unwrap(), EWeatherPage));
         menus.push(MenuItem::new(String::<20>::from_str("日历").unwrap(), ECalendarPage));
         menus.push(MenuItem::new(String::<20>::from_str("游戏").unwrap(), EChip8Page));
+        menus.push(MenuItem::new(String::<20>::from_str("间歇").unwrap(), EIntervalPage));
+        menus.push(MenuItem::new(String::<20>::from_str("闹钟").unwrap(), EAlarmPage));
+        menus.push(MenuItem::new(String::<20>::from_str("统计").unwrap(), EStatsPage));
+        menus.push(MenuItem::new(String::<20>::from_str("网络").unwrap(), ENetworkPage));
         menus.push(MenuItem::new(String::<20>::from_str("设置").unwrap(), ESettingPage));
 
         Self{
@@ -212,6 +258,11 @@ impl Page for  MainPage{
 
         loop {
             if  None == self.current_page {
+                //菜单中收到闹钟直接响铃
+                if let Ok(id) = ALARM_RING_CHANNEL.try_receive() {
+                    Self::ring_alarm(id, spawner).await;
+                    self.back().await;
+                }
                 self.render().await;
                 Timer::after(Duration::from_millis(50)).await;
                 continue;
@@ -224,42 +275,52 @@ impl Page for  MainPage{
                 }
                 EClockPage => {
                     let mut clock_page = ClockPage::new();
-                    clock_page.bind_event().await;
-                    clock_page.run(spawner).await;
-
-                    //切换到主页并绑定事件
+                    Self::run_page(&mut clock_page, spawner).await;
                     self.back().await;
                 }
                 ETimerPage => {
                     let mut timer_page = TimerPage::new();
-                    timer_page.bind_event().await;
-                    timer_page.run(spawner).await;
+                    Self::run_page(&mut timer_page, spawner).await;
                     self.back().await;
                 }
                 EWeatherPage => {
                     let mut clock_page = WeatherPage::new();
-                    clock_page.bind_event().await;
-                    clock_page.run(spawner).await;
+                    Self::run_page(&mut clock_page, spawner).await;
                     self.back().await;
                 }
                 ECalendarPage => {
                     let mut calendar_page = CalendarPage::new();
-                    calendar_page.bind_event().await;
-                    calendar_page.run(spawner).await;
+                    Self::run_page(&mut calendar_page, spawner).await;
                     self.back().await;
                 }
                 EChip8Page => {
                     let mut games_page = GamesPage::new();
-                    games_page.bind_event().await;
-                    games_page.run(spawner).await;
-
-                    //切换到主页并绑定事件
+                    Self::run_page(&mut games_page, spawner).await;
+                    self.back().await;
+                }
+                EIntervalPage => {
+                    let mut interval_page = IntervalPage::new();
+                    Self::run_page(&mut interval_page, spawner).await;
+                    self.back().await;
+                }
+                EAlarmPage => {
+                    let mut alarm_page = AlarmPage::new();
+                    Self::run_page(&mut alarm_page, spawner).await;
+                    self.back().await;
+                }
+                EStatsPage => {
+                    let mut stats_page = StatsPage::new();
+                    Self::run_page(&mut stats_page, spawner).await;
+                    self.back().await;
+                }
+                ENetworkPage => {
+                    let mut network_page = NetworkPage::new();
+                    Self::run_page(&mut network_page, spawner).await;
                     self.back().await;
                 }
                 ESettingPage =>{
                     let mut qrcode_page = SettingPage::new();
-                    qrcode_page.bind_event().await;
-                    qrcode_page.run(spawner).await;
+                    Self::run_page(&mut qrcode_page, spawner).await;
                     self.back().await;
                 }
                 _ => { self.back().await;}
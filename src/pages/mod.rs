@@ -9,6 +9,12 @@ mod games_page;
 mod timer_page;
 mod weather_page;
 mod calendar_page;
+mod stats_page;
+mod alarm_page;
+mod alarm_ring_page;
+mod interval_page;
+mod wifi_page;
+mod network_page;
 pub(crate) mod setting_page;
 pub mod init_page;
 
@@ -20,6 +26,10 @@ enum PageEnum {
     EWeatherPage,
     ECalendarPage,
     EChip8Page,
+    EIntervalPage,
+    EAlarmPage,
+    EStatsPage,
+    ENetworkPage,
     ESettingPage,
 
 }
@@ -43,6 +53,8 @@ pub trait Page {
 
     async fn  run(&mut self,spawner: Spawner);
     async fn bind_event(&mut self);
+    //被闹钟等覆盖后恢复时重新绘制
+    fn redraw(&mut self) {}
 
     fn mut_by_ptr<'a,T>(ptr:Option<usize>)->Option<&'a mut T>{
         unsafe {
@@ -0,0 +1,187 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::prelude::DrawTarget;
+use embedded_graphics::text::Text;
+use esp_println::println;
+use heapless::Vec;
+use lcd_drivers::color::TwoBitColor;
+use u8g2_fonts::U8g2TextStyle;
+use u8g2_fonts::fonts;
+
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::pages::Page;
+use crate::wifi::{CONNECTED_SSID, LINK_STATUS, scan_networks, STACK_MUT, WIFI_STATE, WifiNetState};
+
+const LINE_HEIGHT:i32 = 14;
+const HISTORY_LINES:usize = 3;//屏幕只显示最近几次断开
+
+///网络状态：当前连接、地址和最近的断开记录，排查请求失败时先看网络
+///按键 1 重新扫描刷新信号强度，按键 5 返回
+pub struct NetworkPage{
+    running:bool,
+    need_render:bool,
+    refresh:bool,
+    scanning:bool,
+}
+
+impl NetworkPage {
+
+    //时长显示，不到一分钟显示秒
+    fn duration_text(secs:u64)->String{
+        if secs < 60 {
+            format!("{}秒",secs)
+        } else if secs < 3600 {
+            format!("{}分{}秒",secs / 60,secs % 60)
+        } else {
+            format!("{}时{}分",secs / 3600,secs / 60 % 60)
+        }
+    }
+
+    async fn lines(&self)->Vec<String,10>{
+        let now = Instant::now().as_secs();
+        let mut lines = Vec::new();
+
+        let state = match *WIFI_STATE.lock().await {
+            Some(WifiNetState::WifiConnected) => "已连接",
+            Some(WifiNetState::WifiConnecting) => "连接中",
+            Some(WifiNetState::WifiDisconnected) => "未连接",
+            Some(WifiNetState::WifiStopped) => "已关闭",
+            None => "未启动",
+        };
+        let ssid = CONNECTED_SSID.lock().await.clone().unwrap_or_default();
+        if self.scanning {
+            let _ = lines.push(format!("{} {}  扫描中...",state,ssid));
+        } else {
+            let _ = lines.push(format!("{} {}",state,ssid));
+        }
+
+        let status = LINK_STATUS.lock().await;
+        let connected = !ssid.is_empty();
+        match status.ap.as_ref().filter(|_| connected) {
+            Some(ap) => {
+                let _ = lines.push(format!("信号：{}dBm  信道：{}",ap.signal_strength,ap.channel));
+                let b = ap.bssid;
+                let _ = lines.push(format!("BSSID：{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",b[0],b[1],b[2],b[3],b[4],b[5]));
+            }
+            None => {
+                let _ = lines.push(String::from("信号：-"));
+                let _ = lines.push(String::from("BSSID：-"));
+            }
+        }
+
+        match unsafe { STACK_MUT }.and_then(|stack| stack.config_v4()).filter(|_| connected) {
+            Some(config) => {
+                let _ = lines.push(format!("IP：{}",config.address));
+                let gateway = config.gateway.map(|v| format!("{}",v)).unwrap_or(String::from("-"));
+                let dns = config.dns_servers.first().map(|v| format!("{}",v)).unwrap_or(String::from("-"));
+                let _ = lines.push(format!("网关：{}  DNS：{}",gateway,dns));
+            }
+            None => {
+                let _ = lines.push(String::from("IP：-"));
+                let _ = lines.push(String::from("网关：-  DNS：-"));
+            }
+        }
+
+        let uptime = status.connected_at.map(|v| Self::duration_text(now - v)).unwrap_or(String::from("-"));
+        let stopped = status.stopped_at.map(|v| format!("{}前",Self::duration_text(now - v))).unwrap_or(String::from("-"));
+        let _ = lines.push(format!("连接：{}  关闭：{}",uptime,stopped));
+
+        //最近的断开在前
+        for (time,reason) in status.disconnects.iter().rev().take(HISTORY_LINES) {
+            let _ = lines.push(format!("{}前 {}",Self::duration_text(now - time),reason.text()));
+        }
+        if status.disconnects.is_empty() {
+            let _ = lines.push(String::from("没有断开记录"));
+        }
+        lines
+    }
+
+    fn back(&mut self){
+        self.running = false;
+    }
+}
+
+impl Page for NetworkPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            refresh: false,
+            scanning: false,
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            let lines = self.lines().await;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+                let style =
+                    U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                for (index,line) in lines.iter().enumerate() {
+                    let _ = Text::new(line.as_str(), Point::new(0, 12 + LINE_HEIGHT * index as i32), style.clone()).draw(display);
+                }
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        let mut last_secs = Instant::now().as_secs();
+        loop {
+            if !self.running {
+                break;
+            }
+
+            if self.refresh {
+                self.refresh = false;
+                self.scanning = true;
+                self.need_render = true;
+                self.render().await;
+                let _ = scan_networks().await;
+                println!("network status refreshed");
+                self.scanning = false;
+                self.need_render = true;
+            }
+
+            //时长每秒刷新
+            let now = Instant::now().as_secs();
+            if now != last_secs {
+                last_secs = now;
+                self.need_render = true;
+            }
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        event::on_target(EventType::KeyShort(1),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.refresh = true;
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(5),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.back();
+            });
+        }).await;
+    }
+}
@@ -28,17 +28,19 @@ use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
 use crate::event;
 use crate::event::EventType;
 use crate::pages::Page;
-use crate::storage::{init_storage_area, NvsStorage, WIFI_INFO};
+use crate::pages::wifi_page::WifiPage;
+use crate::storage::{init_storage_area, NvsStorage, OTHER_INFO, WIFI_INFO};
 use crate::weather::get_weather;
 use crate::widgets::qrcode_widget::QrcodeWidget;
-use crate::wifi::{finish_wifi, IP_ADDRESS,  use_wifi,  WIFI_MODEL, WifiNetError};
+use crate::wifi::{IP_ADDRESS, WIFI_MODEL, WifiModel};
 use crate::web_service::{web_service,STOP_WEB_SERVICE};
 
 pub struct SettingPage {
     need_render:bool,
     running:bool,
     long_start_time:u64,
-    ip:String<20>
+    ip:String<20>,
+    open_wifi:bool,//按键 3 进入 wifi 网络管理
 }
 
 impl SettingPage {
@@ -53,6 +55,7 @@ impl Page for SettingPage {
             running: false,
             long_start_time: 0,
             ip: Default::default(),
+            open_wifi: false,
         }
     }
 
@@ -81,14 +84,24 @@ impl Page for SettingPage {
                                                , Size::new(display.bounding_box().size.width - display.bounding_box().size.height,60));
                 let mut clipped_display = display.clipped(&clipping_area);
 
+                //STA 模式下局域网中也可以用 mDNS 名称访问，不受地址变化影响
+                let mut address = format!("地址：{}",url);
+                if let Some(WifiModel::STA) = *WIFI_MODEL.lock().await {
+                    if let Some(other) = OTHER_INFO.lock().await.as_ref() {
+                        address.push_str(&format!(" 或 http://{}.local/config",other.device_name()));
+                    }
+                }
                 TextBox::new(
-                    format!("地址：{}",url).as_str(),
+                    address.as_str(),
                     clipping_area,
                     style.clone(),
                 )
                     .draw(&mut clipped_display);
 
 
+                let _ = Text::new("按键3管理wifi网络", Point::new(display.bounding_box().size.height as i32, display.bounding_box().size.height as i32 - 4), style.clone())
+                    .draw(display);
+
                 if self.long_start_time > 0 {
                     let secs =Instant::now().as_secs() - self.long_start_time;
                     let _ = Text::new( format!("已长按：{} 秒",secs).as_str(), Point::new(display.bounding_box().size.height as i32, 80), style.clone())
@@ -110,7 +123,13 @@ impl Page for SettingPage {
             if !self.running {
                 break;
             }
-            crate::wifi::refresh_last_time().await;
+            if self.open_wifi {
+                self.open_wifi = false;
+                let mut wifi_page = WifiPage::new();
+                wifi_page.bind_event().await;
+                wifi_page.run(spawner).await;
+                self.bind_event().await;
+            }
             self.need_render = true;
             self.render().await;
             Timer::after(Duration::from_millis(50)).await;
@@ -131,6 +150,13 @@ impl Page for SettingPage {
         }).await;
 
 
+        event::on_target(EventType::KeyShort(3),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.open_wifi = true;
+            });
+        }).await;
+
         event::on_target(EventType::KeyLongStart(5),Self::mut_to_ptr(self),  move |info|  {
             return Box::pin(async move {
                 let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr.clone()).unwrap();
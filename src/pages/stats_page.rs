@@ -0,0 +1,296 @@
+use alloc::boxed::Box;
+use alloc::format;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::{Point, Size};
+use embedded_graphics::prelude::{DrawTarget, OriginDimensions};
+use embedded_graphics::text::Text;
+use esp_println::println;
+use heapless::Vec;
+use lcd_drivers::color::TwoBitColor;
+use time::{Date, OffsetDateTime, UtcOffset};
+use u8g2_fonts::U8g2TextStyle;
+use u8g2_fonts::fonts;
+
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::pages::Page;
+use crate::storage::{TIMER_LOG_SLOTS, TIMER_LOG_STATE, WORK_ITEM_INFO};
+use crate::widgets::bar_chart::{BarChart, format_secs};
+use crate::worldtime::{get_clock, sync_time_success};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Period {
+    Today,
+    Week,
+    Month,
+    Daily,//最近几天每天的合计
+}
+
+//每日合计显示的天数
+const DAY_COUNT:usize = 7;
+
+impl Period {
+    fn title(&self)->&'static str{
+        match self {
+            Period::Today => "今日",
+            Period::Week => "本周",
+            Period::Month => "本月",
+            Period::Daily => "每日",
+        }
+    }
+
+    fn next(&self)->Self{
+        match self {
+            Period::Today => Period::Week,
+            Period::Week => Period::Month,
+            Period::Month => Period::Daily,
+            Period::Daily => Period::Daily,
+        }
+    }
+
+    fn prev(&self)->Self{
+        match self {
+            Period::Today => Period::Today,
+            Period::Week => Period::Today,
+            Period::Month => Period::Week,
+            Period::Daily => Period::Month,
+        }
+    }
+
+    //统计区间的第一天
+    fn first_day(&self,today:Date)->Date{
+        match self {
+            Period::Today => today,
+            Period::Week => today - time::Duration::days(today.weekday().number_days_from_monday() as i64),
+            Period::Month => today.replace_day(1).unwrap(),
+            Period::Daily => today - time::Duration::days(DAY_COUNT as i64 - 1),
+        }
+    }
+}
+
+//只保留统计需要的字段，避免每次切换都读 flash
+struct LogSummary{
+    end_date:Date,
+    interval:u64,
+    work_type:u8,
+}
+
+pub struct StatsPage{
+    running:bool,
+    need_render:bool,
+    period:Period,
+    today:Option<Date>,
+    logs:Vec<LogSummary,TIMER_LOG_SLOTS>,
+    //每个类别 id 的总时长，0 为未分类
+    totals:Vec<(u8,u64),TIMER_LOG_SLOTS>,
+    //每日合计，从早到晚，只在 Period::Daily 时计算
+    days:Vec<(Date,u64),DAY_COUNT>,
+}
+
+impl StatsPage {
+
+    fn local_date(timestamp:u64)->Option<Date>{
+        OffsetDateTime::from_unix_timestamp(timestamp as i64).ok()
+            .map(|v| v.to_offset(UtcOffset::from_hms(8,0,0).unwrap()).date())
+    }
+
+    async fn load_logs(&mut self){
+        self.logs.clear();
+        if let Some(state) = TIMER_LOG_STATE.lock().await.as_ref() {
+            for index in 0..TIMER_LOG_SLOTS {
+                if let Some(log) = state.read_log(index) {
+                    if let Some(end_date) = Self::local_date(log.end_timestamp) {
+                        let _ = self.logs.push(LogSummary{
+                            end_date,
+                            interval: log.interval,
+                            work_type: log.work_type,
+                        });
+                    }
+                }
+            }
+        }
+        println!("stats logs:{}",self.logs.len());
+    }
+
+    fn calculate(&mut self){
+        self.totals.clear();
+        self.days.clear();
+        let Some(today) = self.today else { return; };
+        let first_day = self.period.first_day(today);
+        if self.period == Period::Daily {
+            for day in 0..DAY_COUNT {
+                let _ = self.days.push((first_day + time::Duration::days(day as i64), 0));
+            }
+        }
+        for log in self.logs.iter() {
+            if log.end_date < first_day || log.end_date > today {
+                continue;
+            }
+            match self.totals.iter_mut().find(|v| v.0 == log.work_type) {
+                Some(total) => total.1 += log.interval,
+                None => { let _ = self.totals.push((log.work_type,log.interval)); }
+            }
+            if let Some(day) = self.days.iter_mut().find(|v| v.0 == log.end_date) {
+                day.1 += log.interval;
+            }
+        }
+    }
+
+    fn next_period(&mut self){
+        self.period = self.period.next();
+        self.calculate();
+        self.need_render = true;
+    }
+
+    fn prev_period(&mut self){
+        self.period = self.period.prev();
+        self.calculate();
+        self.need_render = true;
+    }
+
+    fn back(&mut self){
+        self.running = false;
+    }
+}
+
+impl Page for StatsPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            period: Period::Today,
+            today: None,
+            logs: Vec::new(),
+            totals: Vec::new(),
+            days: Vec::new(),
+        }
+    }
+
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+                let style =
+                    U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+
+                if self.today.is_none() {
+                    let _ = Text::new("同步时间...", Point::new(0,50), style.clone()).draw(display);
+                    RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                    return;
+                }
+
+                let total:u64 = self.totals.iter().map(|v| v.1).sum();
+                let title = format!("< {} >  合计 {}",self.period.title(),format_secs(total));
+                let _ = Text::new(title.as_str(), Point::new(0, 12), style.clone()).draw(display);
+
+                //类别名称借用自 WORK_ITEM_INFO，画完后释放锁再等待刷新
+                {
+                    let work_items = WORK_ITEM_INFO.lock().await;
+                    let day_labels:Vec<alloc::string::String,DAY_COUNT> = self.days.iter()
+                        .map(|v| format!("{:02}/{:02}",v.0.month() as u8,v.0.day()))
+                        .collect();
+                    let mut items:Vec<(&str,u64),12> = Vec::new();
+                    if self.period == Period::Daily {
+                        for (label,day) in day_labels.iter().zip(self.days.iter()) {
+                            let _ = items.push((label.as_str(),day.1));
+                        }
+                    }else{
+                        //已删除的类别合并为未知
+                        for (id,value) in self.totals.iter() {
+                            let name = if *id == 0 {
+                                "未分类"
+                            } else {
+                                work_items.as_ref().and_then(|v| v.name(*id)).unwrap_or("未知")
+                            };
+                            match items.iter_mut().find(|v| v.0 == name) {
+                                Some(item) => item.1 += *value,
+                                None => { let _ = items.push((name,*value)); }
+                            }
+                        }
+                        //时长多的排在前面
+                        items.sort_unstable_by(|a,b| b.1.cmp(&a.1));
+                    }
+
+                    if total == 0 {
+                        let _ = Text::new("暂无记录", Point::new(0, 40), style.clone()).draw(display);
+                    }else{
+                        let bar_chart = BarChart::new(Point::new(0, 18)
+                                                      , Size::new(display.size().width, display.size().height - 18)
+                                                      , TwoBitColor::Black, TwoBitColor::White, items);
+                        let _ = bar_chart.draw(display);
+                    }
+                }
+
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        self.load_logs().await;
+        loop {
+            if !self.running {
+                break;
+            }
+
+            //时间同步后或跨天时重新统计
+            if sync_time_success() {
+                if let Some(clock) = get_clock() {
+                    let today = clock.local().await.date();
+                    if self.today != Some(today) {
+                        self.today = Some(today);
+                        self.calculate();
+                        self.need_render = true;
+                    }
+                }
+            }
+
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        event::on_target(EventType::WheelFront,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.next_period();
+            });
+        }).await;
+        event::on_target(EventType::WheelBack,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.prev_period();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(1),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.next_period();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(2),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.prev_period();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(5),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.back();
+            });
+        }).await;
+    }
+}
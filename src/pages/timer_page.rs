@@ -21,37 +21,129 @@ use embedded_layout::layout::linear::LinearLayout;
 use embedded_layout::object_chain::Chain;
 use esp_println::println;
 use futures::FutureExt;
+use hal::macros::ram;
+use heapless::Vec;
 use lcd_drivers::color::TwoBitColor;
-use u8g2_fonts::U8g2TextStyle;
+use lcd_drivers::uc1638::prelude::Display2in7;
+use u8g2_fonts::{FontRenderer, U8g2TextStyle};
 use u8g2_fonts::fonts;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
 
 use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
 use crate::ec11::RotateState;
 use crate::event;
 use crate::event::EventType;
+use crate::model::timer_log::{FinishType, TimerLog};
 use crate::pages::{ Page};
 use crate::pages::main_page::MainPage;
 use crate::request::{RequestClient, ResponseData};
+use crate::sleep::{clear_wakeup, get_rtc_ms, refresh_active_time, set_wakeup_rtc_ms, to_sleep, WakeupTask};
 use crate::sound::{player_buzzer, SoundType, stop_buzzer};
-use crate::wifi::use_wifi;
-use crate::worldtime::{CLOCK_SYNC_TIME_SECOND, get_clock};
+use crate::storage::{POMODORO_INFO, PomodoroStorage, save_timer_log, WORK_ITEM_INFO, WORK_ITEM_MAX, WorkItemEntry};
+use crate::widgets::list_widget::ListWidget;
+use crate::worldtime::{clock_ready, CLOCK_SYNC_TIME_SECOND, get_clock};
+
+//运行中的倒计时保存在 rtc 中，深度睡眠唤醒后恢复
+#[ram(rtc_fast)]
+static mut TIMER_END_RTC_MS:u64 = 0;
+#[ram(rtc_fast)]
+static mut TIMER_BEGIN_COUNT:i32 = 0;
+#[ram(rtc_fast)]
+static mut TIMER_BEGIN_TIMESTAMP:u64 = 0;
+#[ram(rtc_fast)]
+static mut TIMER_WORK_TYPE:u8 = 0;
+#[ram(rtc_fast)]
+static mut TIMER_PAUSED_MS:u64 = 0;
+#[ram(rtc_fast)]
+static mut TIMER_PAUSE_COUNT:u16 = 0;
+
+//正计时的记圈
+const LAP_MAX:usize = 99;
+const LAP_TOP:i32 = 64;
+const LAP_ROW_HEIGHT:i32 = 14;
+
+//是否有睡眠前未结束的倒计时
+pub fn has_rtc_countdown()->bool{
+    unsafe { TIMER_END_RTC_MS > 0 }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum TimerState {
+    Idle,
+    Running,
+    Paused,
+    Finished,
+    Aborted,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum TimerMode {
+    Normal,
+    Pomodoro,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    fn title(&self)->&'static str{
+        match self {
+            PomodoroPhase::Work => "工作",
+            PomodoroPhase::ShortBreak => "短休息",
+            PomodoroPhase::LongBreak => "长休息",
+        }
+    }
+}
 
 pub struct TimerPage {
     begin_count:i32,
     need_render:bool,
     current_count:i32,
-    starting:bool,
-    finished:bool,
+    state:TimerState,
+    run_begin:Option<Instant>,//本次计时开始的时间
+    pause_begin:Option<Instant>,//暂停开始的时间
+    end:Option<Instant>,//倒计时结束的时间，暂停期间不变，恢复时加上暂停的时长
+    paused:Duration,//本次计时累计暂停的时长
+    pause_count:u16,
     running:bool,
     loading:bool,
-    error:Option<String>
+    error:Option<String>,
+    begin_timestamp:u64,//开始时还没有同步时间时为 0
+    log_pending:bool,//本次计时还没有保存记录
+    mode:TimerMode,
+    phase:PomodoroPhase,
+    cycle:u32,
+    pomodoro:PomodoroStorage,
+    picking:bool,//开始前选择类别
+    choose_index:usize,
+    work_type:u8,
+    work_items:Vec<WorkItemEntry,WORK_ITEM_MAX>,
+    laps:Vec<Duration,LAP_MAX>,//正计时每圈结束时的总时长
+    scroll:usize,
+    last_tenths:u64,
 }
 
 impl TimerPage {
 
     fn increase(&mut self,speed:f32) {
         self.need_render = true;
-        if !self.starting {
+        if self.picking {
+            if self.choose_index + 1 < self.work_items.len() {
+                self.choose_index += 1;
+            }
+            return;
+        }
+        if self.is_counting_up() {
+            if self.scroll + 1 < self.laps.len() {
+                self.scroll += 1;
+            }
+            return;
+        }
+        if self.state == TimerState::Idle {
             if self.current_count < 3600 * 2 {
                 self.current_count += speed as i32;
             }else{
@@ -62,7 +154,19 @@ impl TimerPage {
 
     fn decrease(&mut self,speed:f32) {
         self.need_render = true;
-        if !self.starting {
+        if self.picking {
+            if self.choose_index > 0 {
+                self.choose_index -= 1;
+            }
+            return;
+        }
+        if self.is_counting_up() {
+            if self.scroll > 0 {
+                self.scroll -= 1;
+            }
+            return;
+        }
+        if self.state == TimerState::Idle {
             if self.current_count > 0 {
                 self.current_count -=  speed as i32;
             }
@@ -90,49 +194,412 @@ impl TimerPage {
         println!("11 speed:{}",speed);
         speed
     }
-    async fn step(&mut self){
-        if self.begin_count == 0 {
-            //正
-            self.current_count +=1;
+    //倒计时剩余的时长，由结束时间计算，页面被闹钟覆盖或渲染慢时不会落后
+    fn remaining(&self)->Duration{
+        let Some(end) = self.end else { return Duration::from_secs(self.current_count.max(0) as u64); };
+        let now = self.pause_begin.unwrap_or_else(Instant::now);
+        end.checked_duration_since(now).unwrap_or(Duration::from_ticks(0))
+    }
 
-        }else{
-            //倒
-            if self.current_count > 0{
-                self.finished = false;
-                self.current_count -=1;
-            }
-            if self.current_count == 0 {
-                self.finished = true;
+    //按剩余时长更新倒计时，到 0 时结束或进入番茄钟的下一阶段，正计时由 count_up_elapsed 计算，不经过这里
+    async fn step(&mut self){
+        let count = ((self.remaining().as_millis() + 999) / 1000) as i32;
+        if count != self.current_count {
+            self.current_count = count;
+            self.need_render = true;
+        }
+        if self.current_count == 0 {
+            if self.mode == TimerMode::Pomodoro && self.phase != PomodoroPhase::LongBreak {
+                self.next_phase().await;
+            }else{
+                self.state = TimerState::Finished;
+                Self::clear_rtc();
+                self.save_log(FinishType::Success).await;
                 println!("player");
                 player_buzzer(SoundType::Music(1)).await;
             }
+            self.need_render = true;
         }
+    }
+
+    //正计时中，用作秒表
+    fn is_counting_up(&self)->bool{
+        self.begin_count == 0 && self.is_active()
+    }
+
+    //正计时的时长，由开始时间减去暂停的时长计算，渲染慢时不会累积误差
+    fn count_up_elapsed(&self)->Duration{
+        let Some(run_begin) = self.run_begin else { return Duration::from_ticks(0); };
+        let end = self.pause_begin.unwrap_or_else(Instant::now);
+        end.duration_since(run_begin).checked_sub(self.paused).unwrap_or(Duration::from_ticks(0))
+    }
 
+    //正计时运行中记录一圈
+    fn lap(&mut self){
+        if self.state != TimerState::Running {
+            return;
+        }
+        //记满后不再记录，标题提示已满
+        if self.laps.is_full() {
+            println!("laps full");
+            return;
+        }
+        let _ = self.laps.push(self.count_up_elapsed());
+        self.scroll = 0;
         self.need_render = true;
     }
 
+    fn format_lap(duration:Duration)->String{
+        let tenths = duration.as_millis() / 100;
+        let second = tenths / 10 % 60;
+        let minute = tenths / 600 % 60;
+        let hour = tenths / 36000;
+        if hour > 0 {
+            format!("{}:{:02}:{:02}.{}",hour,minute,second,tenths % 10)
+        }else{
+            format!("{:02}:{:02}.{}",minute,second,tenths % 10)
+        }
+    }
+
     async fn back(&mut self){
+        if self.picking {
+            self.picking = false;
+            self.need_render = true;
+            return;
+        }
         stop_buzzer().await;
+        if self.is_active() {
+            Self::clear_rtc();
+            self.save_log(FinishType::Abort).await;
+        }
         self.running = false;
     }
 
+    fn is_active(&self)->bool{
+        self.state == TimerState::Running || self.state == TimerState::Paused
+    }
+
+    async fn pause(&mut self){
+        if self.state != TimerState::Running {
+            return;
+        }
+        self.state = TimerState::Paused;
+        self.pause_begin = Some(Instant::now());
+        self.pause_count += 1;
+        Self::clear_rtc();
+    }
+
+    async fn resume(&mut self){
+        if self.state != TimerState::Paused {
+            return;
+        }
+        if let Some(pause_begin) = self.pause_begin.take() {
+            let paused = Instant::now().duration_since(pause_begin);
+            self.paused += paused;
+            self.end = self.end.map(|v| v + paused);
+        }
+        self.state = TimerState::Running;
+        self.save_rtc().await;
+    }
+
+    //手动结束：正计时记为完成，倒计时提前结束记为失败
+    async fn stop(&mut self){
+        if !self.is_active() {
+            return;
+        }
+        self.need_render = true;
+        Self::clear_rtc();
+        if self.begin_count == 0 {
+            self.save_log(FinishType::Success).await;
+            //正计时结束后回到 0，下次开始仍是正计时
+            self.current_count = 0;
+        }else{
+            self.save_log(FinishType::Fail).await;
+        }
+        self.state = TimerState::Idle;
+        if self.mode == TimerMode::Pomodoro {
+            self.reset_pomodoro();
+        }
+    }
+
+    //长按放弃本次计时，记为中断
+    async fn abort(&mut self){
+        if !self.is_active() {
+            return;
+        }
+        self.need_render = true;
+        Self::clear_rtc();
+        self.save_log(FinishType::Abort).await;
+        self.state = TimerState::Aborted;
+    }
+
+    async fn long_press(&mut self){
+        if self.is_active() {
+            self.abort().await;
+        }else{
+            self.toggle_mode().await;
+        }
+    }
+
+    //普通倒计时开始时保存结束时间，睡眠时由定时器唤醒，番茄钟有多个阶段不保存
+    async fn save_rtc(&self){
+        if self.mode != TimerMode::Normal || self.begin_count == 0 {
+            return;
+        }
+        let end_rtc_ms = get_rtc_ms().await + self.remaining().as_millis();
+        unsafe {
+            TIMER_END_RTC_MS = end_rtc_ms;
+            TIMER_BEGIN_COUNT = self.begin_count;
+            TIMER_BEGIN_TIMESTAMP = self.begin_timestamp;
+            TIMER_WORK_TYPE = self.work_type;
+            TIMER_PAUSED_MS = self.paused.as_millis();
+            TIMER_PAUSE_COUNT = self.pause_count;
+        }
+        set_wakeup_rtc_ms(WakeupTask::Timer, end_rtc_ms);
+    }
+
+    fn clear_rtc(){
+        unsafe {
+            TIMER_END_RTC_MS = 0;
+        }
+        clear_wakeup(WakeupTask::Timer);
+    }
+
+    //唤醒后恢复倒计时，已经到时间则直接响铃
+    async fn restore_rtc(&mut self){
+        let end_rtc_ms = unsafe { TIMER_END_RTC_MS };
+        if end_rtc_ms == 0 {
+            return;
+        }
+        let now_rtc_ms = get_rtc_ms().await;
+        unsafe {
+            self.begin_count = TIMER_BEGIN_COUNT;
+            self.begin_timestamp = TIMER_BEGIN_TIMESTAMP;
+            self.log_pending = true;
+            self.work_type = TIMER_WORK_TYPE;
+            self.paused = Duration::from_millis(TIMER_PAUSED_MS);
+            self.pause_count = TIMER_PAUSE_COUNT;
+        }
+        self.mode = TimerMode::Normal;
+        self.state = TimerState::Running;
+        let remaining_ms = end_rtc_ms.saturating_sub(now_rtc_ms);
+        println!("restore countdown:{}ms",remaining_ms);
+        self.end = Some(Instant::now() + Duration::from_millis(remaining_ms));
+        self.step().await;
+        self.need_render = true;
+    }
+
+    //计时经过的秒数
+    fn elapsed(&self)->u64{
+        if self.begin_count == 0 {
+            self.count_up_elapsed().as_secs()
+        }else{
+            (self.begin_count - self.current_count).max(0) as u64
+        }
+    }
+
+    //开始一次计时记录
+    async fn begin_log(&mut self){
+        self.run_begin = Some(Instant::now());
+        self.pause_begin = None;
+        self.paused = Duration::from_ticks(0);
+        self.pause_count = 0;
+        self.laps.clear();
+        self.scroll = 0;
+        self.log_pending = true;
+        self.begin_timestamp = match get_clock() {
+            Some(clock) if clock_ready().await => clock.now().await.unix_timestamp() as u64,
+            _ => 0,
+        };
+    }
+
+    //切换正常计时与番茄钟，只在未开始时切换
+    async fn toggle_mode(&mut self){
+        if self.state != TimerState::Idle {
+            return;
+        }
+        self.need_render = true;
+        if self.mode == TimerMode::Normal {
+            if let Some(pomodoro) = POMODORO_INFO.lock().await.as_ref() {
+                self.pomodoro = *pomodoro;
+            }
+            self.mode = TimerMode::Pomodoro;
+            self.reset_pomodoro();
+        }else{
+            self.mode = TimerMode::Normal;
+            self.current_count = 0;
+        }
+    }
+
+    fn reset_pomodoro(&mut self){
+        self.phase = PomodoroPhase::Work;
+        self.cycle = 1;
+        self.current_count = self.pomodoro.work_secs as i32;
+    }
+
+    fn phase_secs(&self,phase:PomodoroPhase)->i32{
+        (match phase {
+            PomodoroPhase::Work => self.pomodoro.work_secs,
+            PomodoroPhase::ShortBreak => self.pomodoro.short_break_secs,
+            PomodoroPhase::LongBreak => self.pomodoro.long_break_secs,
+        }) as i32
+    }
+
+    //番茄钟进入下一阶段，工作结束时保存记录
+    async fn next_phase(&mut self){
+        match self.phase {
+            PomodoroPhase::Work => {
+                self.save_log(FinishType::Success).await;
+                if self.cycle >= self.pomodoro.cycles {
+                    self.phase = PomodoroPhase::LongBreak;
+                }else{
+                    self.phase = PomodoroPhase::ShortBreak;
+                }
+                player_buzzer(SoundType::Tips(1)).await;
+            }
+            PomodoroPhase::ShortBreak => {
+                self.cycle += 1;
+                self.phase = PomodoroPhase::Work;
+                self.begin_log().await;
+                player_buzzer(SoundType::Tips(0)).await;
+            }
+            PomodoroPhase::LongBreak => {}
+        }
+        self.begin_count = self.phase_secs(self.phase);
+        self.current_count = self.begin_count;
+        //下一阶段从上一阶段的结束时间算起
+        let begin = self.end.unwrap_or_else(Instant::now);
+        self.end = Some(begin + Duration::from_secs(self.begin_count as u64));
+    }
+
+    //结束一次计时并保存记录
+    //开始时还没有同步时间的，结束时已同步则用结束时间减去计时和暂停的时长补上开始时间
+    //一直没有同步时间的开始和结束时间都记为 0，表示时间未知，记录仍然保存和上传
+    async fn save_log(&mut self,finish_type:FinishType){
+        if !self.log_pending {
+            return;
+        }
+        self.log_pending = false;
+        let elapsed = self.elapsed();
+        let mut paused = self.paused;
+        if let Some(pause_begin) = self.pause_begin.take() {
+            paused += Instant::now().duration_since(pause_begin);
+        }
+        let now = match get_clock() {
+            Some(clock) if clock_ready().await => Some(clock.now().await.unix_timestamp() as u64),
+            _ => None,
+        };
+        let (begin_timestamp,end_timestamp) = match (self.begin_timestamp,now) {
+            (0,Some(now)) => (now.saturating_sub(elapsed + paused.as_secs()), now),
+            (0,None) => (0, 0),
+            //刚唤醒时时钟可能还没从 rtc 恢复，结束时间不早于开始时间加计时时长
+            (begin,Some(now)) => (begin, now.max(begin + elapsed + paused.as_secs())),
+            (begin,None) => (begin, begin + elapsed + paused.as_secs()),
+        };
+        let log = TimerLog::new(finish_type, begin_timestamp, end_timestamp, elapsed, self.work_type
+                                , paused.as_secs() as u32, self.pause_count);
+        self.begin_timestamp = 0;
+        save_timer_log(&log).await;
+    }
+
     async fn toggle_starting(&mut self){
 
         self.need_render = true;
+        refresh_active_time().await;
 
-        if self.finished {
-            self.starting = false;
-            self.finished = false;
-            stop_buzzer().await;
+        match self.state {
+            TimerState::Running => {
+                self.pause().await;
+            }
+            TimerState::Paused => {
+                self.resume().await;
+            }
+            TimerState::Finished | TimerState::Aborted => {
+                //放弃后恢复到开始前的时长，方便重新开始
+                if self.state == TimerState::Aborted {
+                    self.current_count = self.begin_count;
+                }
+                self.state = TimerState::Idle;
+                stop_buzzer().await;
+                if self.mode == TimerMode::Pomodoro {
+                    self.reset_pomodoro();
+                }
+            }
+            TimerState::Idle => {
+                self.start().await;
+            }
+        }
+    }
+
+    async fn start(&mut self){
+        //先选择类别，确认后再开始
+        if !self.picking && !self.work_items.is_empty() {
+            self.picking = true;
             return;
         }
+        if self.picking {
+            self.picking = false;
+            self.work_type = self.work_items.get(self.choose_index).map_or(0, |v| v.id);
+        }
+        if self.mode == TimerMode::Pomodoro {
+            if self.current_count <= 0 {
+                return;
+            }
+            //旋钮调整的时长作为本次工作时长
+            self.pomodoro.work_secs = self.current_count as u32;
+            self.reset_pomodoro();
+        }
+        self.state = TimerState::Running;
+        self.begin_count = self.current_count;
+        self.begin_log().await;
+        self.end = if self.begin_count > 0 {
+            Some(Instant::now() + Duration::from_secs(self.begin_count as u64))
+        }else{
+            None
+        };
+        self.save_rtc().await;
+    }
 
-        if self.starting {
-            self.starting = false;
+    //正计时的界面：上方是精确到 0.1 秒的时长，下方是记圈列表，最新的一圈在最上面
+    async fn render_count_up(&mut self,display:&mut Display2in7){
+        let width = display.bounding_box().size.width as i32;
+        let height = display.bounding_box().size.height as i32;
+
+        let font = FontRenderer::new::<fonts::u8g2_font_logisoso32_tn>();
+        let _ = font.render_aligned(
+            Self::format_lap(self.count_up_elapsed()).as_str(),
+            Point::new(width / 2, 10),
+            VerticalPosition::Top,
+            HorizontalAlignment::Center,
+            FontColor::Transparent(TwoBitColor::Black),
+            display,
+        );
+
+        let style =
+            U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+        if self.laps.is_empty() {
+            let tips = match self.state {
+                TimerState::Paused => format!("暂停 {}次，按键2结束",self.pause_count),
+                _ => String::from("按键1记圈，按键2结束"),
+            };
+            let _ = Text::new(tips.as_str(), Point::new(0, LAP_TOP + LAP_ROW_HEIGHT), style).draw(display);
         }else{
-            self.starting = true;
-            self.begin_count = self.current_count;
+            let title = if self.laps.is_full() {
+                format!("共{}圈(已满)  单圈  总计",self.laps.len())
+            } else {
+                format!("共{}圈  单圈  总计",self.laps.len())
+            };
+            let _ = Text::new(title.as_str(), Point::new(0, LAP_TOP), style.clone()).draw(display);
+            let rows = ((height - LAP_TOP) / LAP_ROW_HEIGHT) as usize;
+            for (row,index) in (0..self.laps.len()).rev().skip(self.scroll).take(rows.saturating_sub(1)).enumerate() {
+                let split = self.laps[index];
+                let lap = if index == 0 { split } else { split - self.laps[index - 1] };
+                let text = format!("{:02}  {}  {}",index + 1,Self::format_lap(lap),Self::format_lap(split));
+                let _ = Text::new(text.as_str(), Point::new(0, LAP_TOP + (row as i32 + 1) * LAP_ROW_HEIGHT), style.clone()).draw(display);
+            }
         }
+
+        RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
     }
 
     fn draw_clock<D>(display: &mut D, time: &str) -> Result<(), D::Error>
@@ -169,11 +636,28 @@ impl Page for TimerPage {
             begin_count:0,
             current_count:0,
             need_render:true,
-            starting:false,
-            finished: false,
+            state: TimerState::Idle,
+            run_begin: None,
+            pause_begin: None,
+            end: None,
+            paused: Duration::from_ticks(0),
+            pause_count: 0,
             running:true,
             loading: false,
             error: None,
+            begin_timestamp: 0,
+            log_pending: false,
+            mode: TimerMode::Normal,
+            phase: PomodoroPhase::Work,
+            cycle: 1,
+            pomodoro: PomodoroStorage::default(),
+            picking: false,
+            choose_index: 0,
+            work_type: 0,
+            work_items: Vec::new(),
+            laps: Vec::new(),
+            scroll: 0,
+            last_tenths: 0,
         }
     }
     async fn bind_event(&mut self) {
@@ -185,11 +669,22 @@ impl Page for TimerPage {
                 mut_ref.toggle_starting().await;
             });
         }).await;
+        event::on_target(EventType::KeyLongStart(3),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.long_press().await;
+            });
+        }).await;
         event::on_target(EventType::KeyShort(2),Self::mut_to_ptr(self),  move |info|  {
             println!("current_page:" );
             return Box::pin(async move {
                 let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
-                mut_ref.decrease(5.0);
+                //计时中按键 2 结束本次计时
+                if mut_ref.is_active() {
+                    mut_ref.stop().await;
+                }else{
+                    mut_ref.decrease(5.0);
+                }
                 println!("count_down_page:{}",mut_ref.current_count );
             });
         }).await;
@@ -205,7 +700,12 @@ impl Page for TimerPage {
             println!("current_page:" );
             return Box::pin(async move {
                 let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
-                mut_ref.increase(5.0);
+                //正计时中按键 1 记圈
+                if mut_ref.is_counting_up() {
+                    mut_ref.lap();
+                }else{
+                    mut_ref.increase(5.0);
+                }
                 println!("count_down_page:{}",mut_ref.current_count );
             });
         }).await;
@@ -247,13 +747,36 @@ impl Page for TimerPage {
         }).await;
     }
 
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
     async fn render(&mut self)  {
         if self.need_render {
             self.need_render = false;
             if let Some(display) = display_mut() {
                 let _ = display.clear(TwoBitColor::White);
 
-                if self.finished {
+                if self.picking {
+                    let items:Vec<&str,20> = self.work_items.iter().map(|v|{ v.name.as_str() }).collect();
+                    let mut list_widget = ListWidget::new(Point::new(0, 0)
+                                                          , TwoBitColor::Black
+                                                          , TwoBitColor::White
+                                                          , display.bounding_box().size
+                                                          , items
+                    );
+                    list_widget.choose(self.choose_index);
+                    let _ = list_widget.draw(display);
+                    RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                    return;
+                }
+
+                if self.is_counting_up() {
+                    self.render_count_up(display).await;
+                    return;
+                }
+
+                if self.state == TimerState::Finished {
                     //闪烁一下
                     if Instant::now().as_secs() % 2 == 0 {
                         RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
@@ -268,6 +791,32 @@ impl Page for TimerPage {
 
                 Self::draw_clock(display,time.as_str());
 
+                if self.mode == TimerMode::Pomodoro {
+                    let style =
+                        U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                    let title = format!("番茄钟 {} {}/{}",self.phase.title(),self.cycle,self.pomodoro.cycles);
+                    let _ = Text::new(title.as_str(), Point::new(0, 12), style).draw(display);
+                }
+
+                let state_title = match self.state {
+                    TimerState::Paused => Some(format!("暂停 {}次",self.pause_count)),
+                    TimerState::Aborted => Some(String::from("已放弃")),
+                    _ => None,
+                };
+                if let Some(state_title) = state_title {
+                    let style =
+                        U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                    let _ = Text::new(state_title.as_str(), Point::new(display.bounding_box().size.width as i32 - 60, 12), style).draw(display);
+                }
+
+                if self.work_type > 0 {
+                    if let Some(name) = self.work_items.iter().find(|v| v.id == self.work_type).map(|v| v.name.as_str()) {
+                        let style =
+                            U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                        let _ = Text::new(format!("类别：{}",name).as_str(), Point::new(0, display.bounding_box().size.height as i32 - 4), style).draw(display);
+                    }
+                }
+
                 RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
             }
         }
@@ -275,26 +824,37 @@ impl Page for TimerPage {
 
     async fn run(&mut self,spawner: Spawner) {
         self.running = true;
-        let mut last_time = 0 ;
+        if let Some(work_items) = WORK_ITEM_INFO.lock().await.as_ref() {
+            self.work_items = work_items.items.clone();
+        }
+        self.restore_rtc().await;
+        refresh_active_time().await;
         loop {
             if !self.running {
                 break;
             }
 
-            if self.starting && !self.finished {
-                if last_time == 0 {
-                    last_time = Instant::now().as_secs();
-                }
-                if Instant::now().as_secs() > last_time {
-                    last_time = Instant::now().as_secs();
-                    self.step().await;
+            if self.is_counting_up() {
+                //正计时显示精确到 0.1 秒，变化时才刷新
+                let tenths = self.count_up_elapsed().as_millis() / 100;
+                if tenths != self.last_tenths {
+                    self.last_tenths = tenths;
+                    self.current_count = (tenths / 10) as i32;
+                    self.need_render = true;
                 }
+            }else if self.state == TimerState::Running {
+                self.step().await;
             }
-            if self.finished {
+            if self.state == TimerState::Finished {
                 self.need_render = true;
             }
 
             self.render().await;
+
+            //倒计时运行中一段时间无操作则深度睡眠，到时间由定时器唤醒
+            if self.state == TimerState::Running && has_rtc_countdown() {
+                to_sleep(Duration::from_secs(0), Duration::from_secs(30)).await;
+            }
             Timer::after(Duration::from_millis(50)).await;
         }
     }
@@ -27,7 +27,7 @@ use crate::pages::Page;
 use crate::request::RequestClient;
 use crate::weather::{get_weather, WEATHER_SYNC_SUCCESS};
 use crate::widgets::battery_widget::BatteryWidget;
-use crate::wifi::{finish_wifi, use_wifi, WIFI_STATE};
+use crate::wifi::WIFI_STATE;
 use crate::worldtime::{get_clock, sync_time_success};
 
 
@@ -81,6 +81,10 @@ impl Page for  WeatherPage{
         }
     }
 
+    fn redraw(&mut self) {
+        self.need_render = true;
+    }
+
     async fn render(&mut self)  {
         if self.need_render {
             self.need_render = false;
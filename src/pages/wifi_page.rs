@@ -0,0 +1,367 @@
+use alloc::boxed::Box;
+use alloc::format;
+use core::fmt::Write;
+use core::net::Ipv4Addr;
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use embedded_graphics::Drawable;
+use embedded_graphics::geometry::Point;
+use embedded_graphics::prelude::{Dimensions, DrawTarget};
+use embedded_graphics::text::Text;
+use esp_println::println;
+use heapless::{String, Vec};
+use lcd_drivers::color::TwoBitColor;
+use u8g2_fonts::U8g2TextStyle;
+use u8g2_fonts::fonts;
+
+use crate::display::{display_mut, RENDER_CHANNEL, RenderInfo};
+use crate::event;
+use crate::event::EventType;
+use crate::pages::Page;
+use crate::storage::{NvsStorage, WIFI_INFO, WIFI_NETWORK_MAX, WifiNetwork, WifiStorage};
+use crate::widgets::list_widget::{list_item_text, ListWidget};
+use crate::wifi::{scan_networks, SCAN_MAX, ScanNetwork};
+
+//编辑页的字段：优先级、IP 配置（只显示，在网页中修改）、删除
+const FIELD_PRIORITY:usize = 0;
+const FIELD_DELETE:usize = 2;
+const FIELD_COUNT:usize = 3;
+
+//输入密码时可选的字符
+const PASSWORD_CHARS:&str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!@#$%^&*()-_=+.,?/:;~ ";
+
+enum View{
+    List,//已保存的网络
+    Edit(usize),
+    Scan,//扫描到的网络，选择后新增
+    Password(usize),//输入扫描列表中网络的密码
+}
+
+///保存的 wifi 网络，旋钮选择，按键 3 进入/确认，按键 1/2 调整优先级，按键 5 返回
+///输入密码时旋钮选择字符，按键 3 添加字符，按键 2 删除，按键 1 保存
+pub struct WifiPage{
+    running:bool,
+    need_render:bool,
+    networks:Vec<WifiNetwork,WIFI_NETWORK_MAX>,
+    scanned:Vec<ScanNetwork,SCAN_MAX>,
+    view:View,
+    choose_index:usize,
+    field:usize,
+    scanning:bool,
+    password:String<64>,
+    char_index:usize,
+}
+
+impl WifiPage {
+
+    fn list_items(&self)->Vec<String<20>,20>{
+        let mut items = Vec::new();
+        match self.view {
+            View::List => {
+                for network in self.networks.iter() {
+                    let text = format!("{} {}",network.priority,network.ssid);
+                    let _ = items.push(list_item_text(text.as_str()));
+                }
+                if self.networks.len() < WIFI_NETWORK_MAX {
+                    let _ = items.push(list_item_text("+ 新增"));
+                }
+            }
+            View::Edit(index) => {
+                let network = &self.networks[index];
+                let mut text:String<40> = String::new();
+                let _ = write!(text,"优先级：{}",network.priority);
+                let _ = items.push(list_item_text(text.as_str()));
+                let text = match network.static_ip {
+                    Some(static_ip) => format!("IP：{}/{}",Ipv4Addr::from(static_ip.address),static_ip.prefix_len),
+                    None => alloc::string::String::from("IP：DHCP"),
+                };
+                let _ = items.push(list_item_text(text.as_str()));
+                let _ = items.push(list_item_text("删除"));
+            }
+            View::Scan => {
+                for network in self.scanned.iter() {
+                    let text = format!("{} {}",network.signal_strength,network.ssid);
+                    let _ = items.push(list_item_text(text.as_str()));
+                }
+            }
+            View::Password(_) => {}
+        }
+        items
+    }
+
+    fn current_char(&self)->char{
+        PASSWORD_CHARS.chars().nth(self.char_index).unwrap_or(' ')
+    }
+
+    fn increase(&mut self){
+        match self.view {
+            View::Edit(_) => {
+                if self.field + 1 < FIELD_COUNT {
+                    self.field += 1;
+                }
+            }
+            View::Password(_) => {
+                self.char_index = (self.char_index + 1) % PASSWORD_CHARS.chars().count();
+            }
+            _ => {
+                if self.choose_index + 1 < self.list_items().len() {
+                    self.choose_index += 1;
+                }
+            }
+        }
+        self.need_render = true;
+    }
+
+    fn decrease(&mut self){
+        match self.view {
+            View::Edit(_) => {
+                if self.field > 0 {
+                    self.field -= 1;
+                }
+            }
+            View::Password(_) => {
+                let count = PASSWORD_CHARS.chars().count();
+                self.char_index = (self.char_index + count - 1) % count;
+            }
+            _ => {
+                if self.choose_index > 0 {
+                    self.choose_index -= 1;
+                }
+            }
+        }
+        self.need_render = true;
+    }
+
+    //按键 1/2，编辑时调整优先级，输入密码时 1 保存、2 删除最后一个字符
+    async fn change(&mut self,step:i32){
+        match self.view {
+            View::Edit(index) => {
+                if self.field == FIELD_PRIORITY {
+                    let network = &mut self.networks[index];
+                    network.priority = (network.priority as i32 + step).clamp(0,u8::MAX as i32) as u8;
+                    self.need_render = true;
+                }
+            }
+            View::Password(index) => {
+                if step > 0 {
+                    self.add_scanned(index).await;
+                }else{
+                    self.password.pop();
+                }
+                self.need_render = true;
+            }
+            _ => {}
+        }
+    }
+
+    async fn confirm(&mut self){
+        match self.view {
+            View::List => {
+                if self.choose_index < self.networks.len() {
+                    self.view = View::Edit(self.choose_index);
+                    self.field = FIELD_PRIORITY;
+                }else{
+                    self.scanning = true;
+                    self.need_render = true;
+                    self.render().await;
+                    self.scanned = scan_networks().await;
+                    self.scanned.sort_unstable_by(|a,b| b.signal_strength.cmp(&a.signal_strength));
+                    self.scanning = false;
+                    self.view = View::Scan;
+                    self.choose_index = 0;
+                }
+            }
+            View::Edit(index) => {
+                if self.field == FIELD_DELETE {
+                    let network = self.networks.remove(index);
+                    self.save(|wifi_info| { wifi_info.remove_network(&network.ssid); }).await;
+                    self.view = View::List;
+                    self.choose_index = index.min(self.networks.len());
+                }
+            }
+            View::Scan => {
+                let Some(scanned) = self.scanned.get(self.choose_index) else { return; };
+                if scanned.secured() {
+                    self.password.clear();
+                    self.char_index = 0;
+                    self.view = View::Password(self.choose_index);
+                }else{
+                    self.password.clear();
+                    self.add_scanned(self.choose_index).await;
+                }
+            }
+            View::Password(_) => {
+                let _ = self.password.push(self.current_char());
+            }
+        }
+        self.need_render = true;
+    }
+
+    //新增扫描到的网络，排在已保存网络的最后
+    async fn add_scanned(&mut self,index:usize){
+        let Some(scanned) = self.scanned.get(index) else { return; };
+        let network = WifiNetwork{
+            ssid: scanned.ssid.clone(),
+            password: self.password.clone(),
+            priority: 0,
+            static_ip: None,
+        };
+        self.save(|wifi_info| { wifi_info.add_network(network); }).await;
+        self.view = View::List;
+        self.choose_index = 0;
+    }
+
+    //在副本上修改并写入存储，成功后才替换，完成后重新读取列表
+    async fn save<F:FnOnce(&mut WifiStorage)>(&mut self,modify:F){
+        let mut wifi_info = WIFI_INFO.lock().await;
+        if let Some(current) = wifi_info.as_ref() {
+            let mut updated = current.clone();
+            modify(&mut updated);
+            match updated.write() {
+                Ok(_) => {
+                    println!("wifi saved:{}",updated.networks.len());
+                    wifi_info.replace(updated);
+                }
+                Err(e) => { println!("save wifi fail：{:?}",e); }
+            }
+        }
+        if let Some(current) = wifi_info.as_ref() {
+            self.networks = current.networks.clone();
+        }
+    }
+
+    async fn back(&mut self){
+        match self.view {
+            View::List => {
+                self.running = false;
+            }
+            View::Edit(index) => {
+                let network = self.networks[index].clone();
+                self.save(|wifi_info| { wifi_info.add_network(network); }).await;
+                self.view = View::List;
+                self.choose_index = index;
+            }
+            View::Scan | View::Password(_) => {
+                self.view = View::List;
+                self.choose_index = 0;
+            }
+        }
+        self.need_render = true;
+    }
+}
+
+impl Page for WifiPage {
+    fn new() -> Self {
+        Self{
+            running: false,
+            need_render: false,
+            networks: Vec::new(),
+            scanned: Vec::new(),
+            view: View::List,
+            choose_index: 0,
+            field: FIELD_PRIORITY,
+            scanning: false,
+            password: String::new(),
+            char_index: 0,
+        }
+    }
+
+    async fn render(&mut self) {
+        if self.need_render {
+            self.need_render = false;
+            if let Some(display) = display_mut() {
+                let _ = display.clear(TwoBitColor::White);
+                let style =
+                    U8g2TextStyle::new(fonts::u8g2_font_wqy12_t_gb2312b, TwoBitColor::Black);
+                if self.scanning {
+                    let _ = Text::new("正在扫描网络...", Point::new(0, 50), style).draw(display);
+                    RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                    return;
+                }
+                if let View::Password(index) = self.view {
+                    let ssid = self.scanned.get(index).map_or("",|v| v.ssid.as_str());
+                    let _ = Text::new(format!("网络：{}",ssid).as_str(), Point::new(0, 14), style.clone()).draw(display);
+                    let _ = Text::new(format!("密码：{}",self.password).as_str(), Point::new(0, 34), style.clone()).draw(display);
+                    let _ = Text::new(format!("字符：[{}]",self.current_char()).as_str(), Point::new(0, 54), style.clone()).draw(display);
+                    let _ = Text::new("旋钮选择 3添加 2删除 1保存", Point::new(0, 80), style.clone()).draw(display);
+                    RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                    return;
+                }
+                if let View::Scan = self.view {
+                    if self.scanned.is_empty() {
+                        let _ = Text::new("未扫描到网络", Point::new(0, 50), style).draw(display);
+                        RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+                        return;
+                    }
+                }
+
+                let items = self.list_items();
+                let labels:Vec<&str,20> = items.iter().map(|v| v.as_str()).collect();
+                let mut list_widget = ListWidget::new(Point::new(0, 0)
+                                                      , TwoBitColor::Black
+                                                      , TwoBitColor::White
+                                                      , display.bounding_box().size
+                                                      , labels
+                );
+                list_widget.choose(if let View::Edit(_) = self.view { self.field } else { self.choose_index });
+                let _ = list_widget.draw(display);
+                RENDER_CHANNEL.send(RenderInfo { time: 0 }).await;
+            }
+        }
+    }
+
+    async fn run(&mut self, spawner: Spawner) {
+        self.running = true;
+        self.need_render = true;
+        if let Some(wifi_info) = WIFI_INFO.lock().await.as_ref() {
+            self.networks = wifi_info.networks.clone();
+        }
+        loop {
+            if !self.running {
+                break;
+            }
+            self.render().await;
+            Timer::after(Duration::from_millis(50)).await;
+        }
+    }
+
+    async fn bind_event(&mut self) {
+        event::clear().await;
+        event::on_target(EventType::WheelFront,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.increase();
+            });
+        }).await;
+        event::on_target(EventType::WheelBack,Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.decrease();
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(1),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(1).await;
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(2),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.change(-1).await;
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(3),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.confirm().await;
+            });
+        }).await;
+        event::on_target(EventType::KeyShort(5),Self::mut_to_ptr(self),  move |info|  {
+            return Box::pin(async move {
+                let mut_ref:&mut Self =  Self::mut_by_ptr(info.ptr).unwrap();
+                mut_ref.back().await;
+            });
+        }).await;
+    }
+}
@@ -6,8 +6,9 @@ use embedded_tls::{Aes128GcmSha256, NoVerify, TlsConfig, TlsConnection, TlsConte
 use esp_println::println;
 use esp_wifi::wifi::{WifiDevice, WifiStaDevice};
 use reqwless::Error;
+use reqwless::headers::ContentType;
 use reqwless::request::{Method, Request, RequestBuilder};
-use reqwless::response::Response;
+use reqwless::response::{Response, Status};
 use crate::random::RngWrapper;
 
 const BUFFER_SIZE:usize = 4096;
@@ -23,6 +24,7 @@ pub enum RequestError{
     SendError,
     ReadError,
     BufferOver,
+    HttpStatus(Status),
 }
 
 impl From<ConnectError> for RequestError{
@@ -70,6 +72,15 @@ impl RequestClient{
         }
     }
     pub async fn send_request(&mut self, url: &str) -> Result<ResponseData, RequestError> {
+        self.send(Method::GET, url, None, &[]).await
+    }
+
+    /// Send a POST request with a JSON body, non 2xx status is treated as error
+    pub async fn send_post_json(&mut self, url: &str, body: &[u8], headers: &[(&str, &str)]) -> Result<ResponseData, RequestError> {
+        self.send(Method::POST, url, Some(body), headers).await
+    }
+
+    async fn send(&mut self, method: Method, url: &str, body: Option<&[u8]>, headers: &[(&str, &str)]) -> Result<ResponseData, RequestError> {
         if let Some(rest) = url.strip_prefix("https://") {
             println!("Rest: {rest}");
             let (host_and_port, path) = rest.split_once('/').unwrap_or((rest, ""));
@@ -79,7 +90,7 @@ impl RequestClient{
                 .unwrap_or((host_and_port, "443"));
             println!("Host: {host}, port: {port}, path: {path}");
             let port = port.parse::<u16>().map_err(|e|{ RequestError::PortParse(e)})?;
-            self.send_https_request(url, host, port, path).await
+            self.send_https_request(method, url, host, port, path, body, headers).await
         } else if let Some(rest) = url.strip_prefix("http://") {
             println!("Rest: {rest}");
             let (host_and_port, path) = rest.split_once('/').unwrap_or((rest, ""));
@@ -89,7 +100,7 @@ impl RequestClient{
                 .unwrap_or((host_and_port, "80"));
             println!("Host: {host}, port: {port}, path: {path}");
             let port = port.parse::<u16>().map_err(|e|{ RequestError::PortParse(e)})?;
-            self.send_plain_http_request(url, host, port, path).await
+            self.send_plain_http_request(method, url, host, port, path, body, headers).await
         } else {
             Err(RequestError::UnsupportedScheme)
         }
@@ -99,10 +110,13 @@ impl RequestClient{
     /// Send a plain HTTP request
     async fn send_plain_http_request(
         &mut self,
+        method: Method,
         url: &str,
         host: &str,
         port: u16,
         path: &str,
+        body: Option<&[u8]>,
+        headers: &[(&str, &str)],
     ) -> Result<ResponseData, RequestError> {
         println!("Send plain HTTP request to path {path} at host {host}:{port}");
 
@@ -117,16 +131,28 @@ impl RequestClient{
         socket.connect(remote_endpoint).await?;
         println!("Connected to HTTP server");
 
-        let mut request = Request::get(url).host(host).build();
-
-        request.write(&mut socket).await?;
+        match body {
+            Some(body) => {
+                let request = Request::new(method, url).host(host).headers(headers)
+                    .content_type(ContentType::ApplicationJson).body(body).build();
+                request.write(&mut socket).await?;
+            }
+            None => {
+                let request = Request::new(method, url).host(host).headers(headers).build();
+                request.write(&mut socket).await?;
+            }
+        }
 
 
         let mut headers_buf = [0_u8; 1024];
         let mut buf = [0_u8; 4096];
-        let response = Response::read(&mut socket, Method::GET, &mut headers_buf).await?;
+        let response = Response::read(&mut socket, method, &mut headers_buf).await?;
 
         println!("Response status: {:?}", response.status);
+        if !response.status.is_successful() {
+            socket.close();
+            return Err(RequestError::HttpStatus(response.status));
+        }
 
         let total_length = response.body().reader().read_to_end(&mut buf).await?;
 
@@ -140,10 +166,13 @@ impl RequestClient{
     /// Send an HTTPS request
     async fn send_https_request(
         &mut self,
+        method: Method,
         url: &str,
         host: &str,
         port: u16,
         path: &str,
+        body: Option<&[u8]>,
+        headers: &[(&str, &str)],
     ) -> Result<ResponseData, RequestError>  {
         println!("Send HTTPs request to path {path} at host {host}:{port}");
 
@@ -172,14 +201,24 @@ impl RequestClient{
             .await?;
         println!("TLS handshake succeeded");
 
-        let request = Request::get(url).host(host).build();
-        request.write(&mut tls).await?;
+        match body {
+            Some(body) => {
+                let request = Request::new(method, url).host(host).headers(headers)
+                    .content_type(ContentType::ApplicationJson).body(body).build();
+                request.write(&mut tls).await?;
+            }
+            None => {
+                let request = Request::new(method, url).host(host).headers(headers).build();
+                request.write(&mut tls).await?;
+            }
+        }
 
         let mut headers_buf = [0_u8; 1024];
         let mut buf = [0_u8; 4096];
-        let response = Response::read(&mut tls, Method::GET, &mut headers_buf).await?;
+        let response = Response::read(&mut tls, method, &mut headers_buf).await?;
 
         println!("Response status: {:?}", response.status);
+        let status = response.status;
 
         let total_length = response.body().reader().read_to_end(&mut buf).await?;
 
@@ -195,6 +234,10 @@ impl RequestClient{
         println!("Close TCP socket");
         socket.close();
 
+        if !status.is_successful() {
+            return Err(RequestError::HttpStatus(status));
+        }
+
         println!("Read {} bytes", total_length);
 
         return Ok(crate::request::ResponseData{ data: buf, length: total_length });
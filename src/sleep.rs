@@ -18,6 +18,16 @@ pub static LAST_ACTIVE_TIME:Mutex<CriticalSectionRawMutex,Instant> = Mutex::new(
 pub static mut WAKEUP_PINS:  Vec<(&'static mut dyn RtcPinWithResistors, WakeupLevel),5> = Vec::new();
 #[ram(rtc_fast)]
 static mut WHEN_SLEEP_RTC_MS:u64 = 0;
+//需要定时唤醒的 rtc 时间，按 WakeupTask 区分，0 表示没有
+#[ram(rtc_fast)]
+static mut WAKEUP_RTC_MS:[u64;2] = [0;2];
+
+//需要在深度睡眠中定时唤醒的任务
+#[derive(Copy, Clone, Debug)]
+pub enum WakeupTask{
+    Timer = 0,
+    Alarm = 1,
+}
 
 pub async fn refresh_active_time(){
      *LAST_ACTIVE_TIME.lock().await = Instant::now();
@@ -25,6 +35,20 @@ pub async fn refresh_active_time(){
 
 pub async fn to_sleep(sleep_time:Duration,idle_time:Duration){
     if Instant::now().duration_since(*LAST_ACTIVE_TIME.lock().await) > idle_time  {
+        let mut sleep_time = sleep_time;
+        //有定时唤醒任务时，最迟在最早的任务时间唤醒，已经到时间则不睡眠
+        let wakeup_rtc_ms = unsafe{ WAKEUP_RTC_MS }.iter().filter(|v| **v > 0).min().copied().unwrap_or(0);
+        if wakeup_rtc_ms > 0 {
+            let now_rtc_ms = get_rtc_ms().await;
+            if wakeup_rtc_ms <= now_rtc_ms {
+                return;
+            }
+            let wakeup_time = Duration::from_millis(wakeup_rtc_ms - now_rtc_ms);
+            if sleep_time.as_ticks() == 0 || wakeup_time < sleep_time {
+                sleep_time = wakeup_time;
+            }
+        }
+
         //不关wifi,唤醒时运行到wifi部分会卡着
         force_stop_wifi().await;
 
@@ -61,4 +85,16 @@ pub async fn add_rtcio(rtcpin:&'static mut dyn RtcPinWithResistors, wakeup_level
     unsafe {
         WAKEUP_PINS.push((rtcpin,wakeup_level));
     }
+}
+
+pub fn set_wakeup_rtc_ms(task:WakeupTask,rtc_ms:u64){
+    unsafe {
+        WAKEUP_RTC_MS[task as usize] = rtc_ms;
+    }
+}
+
+pub fn clear_wakeup(task:WakeupTask){
+    unsafe {
+        WAKEUP_RTC_MS[task as usize] = 0;
+    }
 }
\ No newline at end of file
@@ -218,6 +218,33 @@ impl <GPIO> PwmPlayer<GPIO>
         (261, 1000), // C4, 1000ms
     ];
 
+    //提示音，开始工作：由低到高
+    const WORK_TIPS: [(u32, u64); 5] = [
+        (523, 150), // C5, 150ms
+        (0, 50),    // Pause, 50ms
+        (659, 150), // E5, 150ms
+        (0, 50),    // Pause, 50ms
+        (784, 300), // G5, 300ms
+    ];
+
+    //提示音，开始休息：由高到低
+    const BREAK_TIPS: [(u32, u64); 5] = [
+        (784, 150), // G5, 150ms
+        (0, 50),    // Pause, 50ms
+        (659, 150), // E5, 150ms
+        (0, 50),    // Pause, 50ms
+        (523, 300), // C5, 300ms
+    ];
+
+    //提示音，阶段切换：三声短响
+    const PHASE_TIPS: [(u32, u64); 5] = [
+        (880, 100), // A5, 100ms
+        (0, 80),    // Pause, 80ms
+        (880, 100), // A5, 100ms
+        (0, 80),    // Pause, 80ms
+        (880, 100), // A5, 100ms
+    ];
+
     //buzzer
     pub async fn player_buzzer(&mut self,sound_type: SoundType){
         let mut melody:Vec<(u32,u64),100> = Vec::new();
@@ -231,12 +258,26 @@ impl <GPIO> PwmPlayer<GPIO>
                     melody = Vec::from_slice(&Self::TWINKLE_TWINKLE).unwrap();
                 }
             }
-            SoundType::Tips(_) => {}
+            SoundType::Tips(n) => {
+                if n == 0 {
+                    melody = Vec::from_slice(&Self::WORK_TIPS).unwrap();
+                }
+                if n == 1 {
+                    melody = Vec::from_slice(&Self::BREAK_TIPS).unwrap();
+                }
+                if n == 2 {
+                    melody = Vec::from_slice(&Self::PHASE_TIPS).unwrap();
+                }
+            }
         }
 
         *self.state.lock().await = PlayerState::Playing;
 
-        let mut times = 5;
+        //提示音只播放一次
+        let mut times = match sound_type {
+            SoundType::Tips(_) => 1,
+            _ => 5,
+        };
 
          'out:loop {
 
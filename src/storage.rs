@@ -1,73 +1,123 @@
-use core::mem::size_of;
-use core::ptr;
+use alloc::vec;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::mutex::Mutex;
+use embassy_sync::signal::Signal;
 use esp_storage::{FlashStorage, FlashStorageError};
-use embedded_storage::{ReadStorage, Storage};
 use esp_println::println;
 use futures::FutureExt;
+use heapless::Vec;
+use core::net::Ipv4Addr;
+use core::fmt::Write;
+use core::str::FromStr;
+use crate::flash::{erase_flash, read_flash, SECTOR_SIZE, write_flash};
+use crate::kv::{KvError, KvStore};
+use crate::mdns::{DEFAULT_DEVICE_NAME, DEVICE_NAME_MAX};
+use crate::model::alarm::{Alarm, ALARM_LABELS};
+use crate::interval::{IntervalBlock, IntervalSequence, IntervalStep};
+use crate::model::timer_log::{FinishType, TimerLog, WorkItem};
+use crate::record::{Decoder, Encode, Encoder, pack, unpack, UnpackError};
 
-pub fn write_flash(flash_addr:u32, bytes: &[u8]) -> Result<(), FlashStorageError> {
-    let mut flash = FlashStorage::new();
+#[derive(Debug)]
+pub enum StorageError{
+    Flash(FlashStorageError),
+    Empty,//没有写入过
+    Corrupt,//校验失败
+    Unsupported,//更新版本固件写入的记录
+    TooLarge,//编码后超出记录区域
+    Full,//键值存储整理后仍然放不下
+}
 
-    let result = flash.write(flash_addr, bytes);
+impl From<FlashStorageError> for StorageError {
+    fn from(e: FlashStorageError) -> Self {
+        StorageError::Flash(e)
+    }
+}
 
-    match result {
-        Ok(_) => { println!("save success");}
-        Err(e) => {
-            println!("save fail：{:?}",e);
+impl From<UnpackError> for StorageError {
+    fn from(e: UnpackError) -> Self {
+        match e {
+            UnpackError::Empty => StorageError::Empty,
+            UnpackError::Corrupt => StorageError::Corrupt,
+            UnpackError::Unsupported => StorageError::Unsupported,
         }
     }
-
-    Ok(())
-
 }
-pub fn read_flash(flash_addr:u32, bytes: &mut [u8]) -> Result<(), FlashStorageError> {
-    let mut flash = FlashStorage::new();
-    flash.read(flash_addr,bytes)
+
+impl From<KvError<FlashStorageError>> for StorageError {
+    fn from(e: KvError<FlashStorageError>) -> Self {
+        match e {
+            KvError::Storage(e) => StorageError::Flash(e),
+            KvError::Full => StorageError::Full,
+            KvError::TooLarge => StorageError::TooLarge,
+            KvError::TypeMismatch => StorageError::Corrupt,
+        }
+    }
 }
 
+const NVS_OFFSET:usize = 0x9000;
 
-fn serialize_storage<T>(storage: &T) -> [u8; size_of::<T>()] {
-    unsafe { ptr::read(storage as *const _ as *const [u8; size_of::<T>()]) }
-}
+//设置保存在 NVS 开头的键值存储中，两半各两个扇区
+const KV_HALF_SIZE:usize = 2 * SECTOR_SIZE;
 
-fn deserialize_storage<T>(data: &[u8]) -> T {
-    unsafe { ptr::read(data.as_ptr() as *const T) }
+pub type Kv = KvStore<FlashStorage>;
+
+pub fn open_kv()->Result<Kv,StorageError>{
+    Ok(KvStore::open(FlashStorage::new(), NVS_OFFSET as u32, KV_HALF_SIZE as u32)?)
 }
 
-pub trait NvsStorage{
-    fn read()->  Result<Self,FlashStorageError>  where Self: Sized;
+//各个设置结构体对键值存储的包装，load/save 在已打开的存储上读写，read/write 每次重新打开
+pub trait NvsStorage: Sized{
+    fn load(kv:&mut Kv)->Result<Self,StorageError>;
+
+    fn save(&self,kv:&mut Kv)->Result<(),StorageError>;
 
-    fn write(&self)-> Result<(), FlashStorageError>;
+    fn read()->  Result<Self,StorageError>{
+        Self::load(&mut open_kv()?)
+    }
+
+    fn write(&self)-> Result<(), StorageError>{
+        self.save(&mut open_kv()?)
+    }
 }
 
+//没有的键按未写入处理
+fn required<T>(value:Option<T>)->Result<T,StorageError>{
+    value.ok_or(StorageError::Empty)
+}
 
+//列表等结构整体编码后保存为一个值，带有结构版本
 macro_rules! impl_storage {
-    ($type:ty, $offset:expr) => {
+    ($type:ty, $key:expr, $size:expr) => {
         impl NvsStorage for $type {
-            fn read() -> Result<Self,FlashStorageError> {
-                let mut buffer = [0u8; size_of::<Self>()];
-                let result = read_flash($offset as u32, &mut buffer);
-                  match result {
-                    Ok(_) => { Ok(deserialize_storage(&buffer)) }
-                    Err(e) => {
-                       Err(e)
-                    }
-                }
+            fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+                Ok(unpack(&required(kv.get_blob($key)?)?)?)
             }
 
-            fn write(&self) -> Result<(), FlashStorageError> {
-                let data = serialize_storage(self);
-                write_flash($offset as u32, &data)
+            fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+                let mut buffer = vec![0u8; $size];
+                let len = pack(self, &mut buffer).ok_or(StorageError::TooLarge)?;
+                kv.set_blob($key, &buffer[..len])?;
+                Ok(())
             }
         }
     };
 }
-const NVS_OFFSET:usize = 0x9000;
 
-const VERSION_STORAGE_OFFSET:usize = NVS_OFFSET + 0x00;
+//计时记录在键值存储之后，每个槽位一条，按顺序追加，游标进入一个扇区时才擦除这个扇区
+//槽位：序号(4) + 同步标记(4) + 记录，同步标记写入时保持擦除后的 0xFF，上传后原地写为 0
+//记录的状态不放在设置中，启动时扫描槽位恢复
+//三个扇区，擦除一个扇区后仍至少保留 128 条，需要 partitions.csv 中把 nvs 扩展到 0x7000
+const TIMER_LOG_DATA_OFFSET:usize = NVS_OFFSET + 2 * KV_HALF_SIZE;
+const TIMER_LOG_RECORD_SIZE:usize = 0x40;
+const TIMER_LOG_AREA_SIZE:usize = 3 * SECTOR_SIZE;
+const TIMER_LOG_SYNCED_OFFSET:usize = 4;
+const TIMER_LOG_HEADER_SIZE:usize = 8;
+
 const INIT_TAG:u32 = 0x1234abcd;
+//存储结构的版本，每次升级需要迁移数据时加一，并在 migrate 中增加对应的步骤
+//1: 直接保存结构体内存的旧格式
+//2: 键值存储
+pub const STORAGE_VERSION:u32 = 2;
 
 #[derive(Debug,Default)]
 pub struct VersionStorage{
@@ -75,91 +125,1041 @@ pub struct VersionStorage{
     pub init_tag:u32,
 }
 
-const WIFI_STORAGE_OFFSET:usize =  VERSION_STORAGE_OFFSET+ size_of::<VersionStorage>();
+impl Encode for VersionStorage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u32(self.version);
+        encoder.u32(self.init_tag);
+    }
 
-#[derive(Debug,Default)]
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            version: decoder.u32()?,
+            init_tag: decoder.u32()?,
+        })
+    }
+}
+
+pub const WIFI_NETWORK_MAX:usize = 5;
+
+//静态 IPv4 配置，不设置时使用 DHCP
+#[derive(Debug,Default,Clone,Copy,Eq,PartialEq)]
+pub struct StaticIp{
+    pub address:[u8;4],
+    pub prefix_len:u8,
+    pub gateway:[u8;4],
+    pub dns_servers:[[u8;4];2],//0.0.0.0 表示不使用
+}
+
+impl StaticIp {
+    //地址可以带前缀长度，如 192.168.1.50/24，不带时按 24 处理，DNS 用逗号分隔，最多两个
+    pub fn parse(address:&str,gateway:&str,dns:&str)->Option<Self>{
+        let (address,prefix_len) = match address.trim().split_once('/') {
+            Some((address,prefix_len)) => (address, prefix_len.trim().parse::<u8>().ok()?),
+            None => (address.trim(), 24),
+        };
+        if prefix_len > 32 {
+            return None;
+        }
+        let mut static_ip = Self{
+            address: Ipv4Addr::from_str(address).ok()?.octets(),
+            prefix_len,
+            gateway: Ipv4Addr::from_str(gateway.trim()).ok()?.octets(),
+            dns_servers: [[0;4];2],
+        };
+        for (index,dns) in dns.split(|c| c == ',' || c == ' ').filter(|v| !v.is_empty()).enumerate() {
+            *static_ip.dns_servers.get_mut(index)? = Ipv4Addr::from_str(dns).ok()?.octets();
+        }
+        Some(static_ip)
+    }
+}
+
+impl Encode for StaticIp {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bytes(&self.address);
+        encoder.u8(self.prefix_len);
+        encoder.bytes(&self.gateway);
+        for dns in self.dns_servers.iter() {
+            encoder.bytes(dns);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            address: decoder.bytes(4)?.try_into().ok()?,
+            prefix_len: decoder.u8()?,
+            gateway: decoder.bytes(4)?.try_into().ok()?,
+            dns_servers: [decoder.bytes(4)?.try_into().ok()?, decoder.bytes(4)?.try_into().ok()?],
+        })
+    }
+}
+
+//保存的一个 wifi 网络，priority 越大越优先
+#[derive(Debug,Default,Clone)]
+pub struct WifiNetwork{
+    pub ssid:heapless::String<32>,
+    pub password:heapless::String<64>,
+    pub priority:u8,
+    pub static_ip:Option<StaticIp>,
+}
+
+impl Encode for WifiNetwork {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.ssid);
+        encoder.str(&self.password);
+        encoder.u8(self.priority);
+        encoder.bool(self.static_ip.is_some());
+        if let Some(static_ip) = self.static_ip.as_ref() {
+            static_ip.encode(encoder);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let ssid = decoder.string()?;
+        let password = decoder.string()?;
+        let priority = decoder.u8()?;
+        let static_ip = if decoder.schema >= 3 && decoder.bool()? {
+            Some(StaticIp::decode(decoder)?)
+        } else {
+            None
+        };
+        Some(Self{ ssid, password, priority, static_ip })
+    }
+}
+
+#[derive(Debug,Default,Clone)]
 pub struct WifiStorage{
-    pub wifi_ssid:heapless::String<32>,
-    pub wifi_password:heapless::String<64>,
+    pub networks:Vec<WifiNetwork,WIFI_NETWORK_MAX>,
     pub wifi_finish:bool
 }
 
-const WEATHER_STORAGE_OFFSET:usize = WIFI_STORAGE_OFFSET+ size_of::<WifiStorage>();
+impl WifiStorage {
+    //同名的网络直接覆盖，列表已满时返回 false
+    pub fn add_network(&mut self,network:WifiNetwork)->bool{
+        if let Some(v) = self.networks.iter_mut().find(|v| v.ssid == network.ssid) {
+            *v = network;
+        }else if self.networks.push(network).is_err() {
+            return false;
+        }
+        self.wifi_finish = true;
+        true
+    }
+
+    pub fn remove_network(&mut self,ssid:&str)->bool{
+        let Some(index) = self.networks.iter().position(|v| v.ssid == ssid) else { return false; };
+        self.networks.remove(index);
+        self.wifi_finish = !self.networks.is_empty();
+        true
+    }
+
+    //优先级最高的网络，扫描不到已保存的网络时使用（可能是隐藏的网络）
+    pub fn preferred(&self)->Option<&WifiNetwork>{
+        self.networks.iter().max_by_key(|v| v.priority)
+    }
+}
+
+impl Encode for WifiStorage {
+    //1: 只有一个网络
+    //2: 多个网络
+    //3: 网络增加静态 IP
+    const SCHEMA:u16 = 3;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(&self.networks);
+        encoder.bool(self.wifi_finish);
+    }
 
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        if decoder.schema < 2 {
+            let network = WifiNetwork{
+                ssid: decoder.string()?,
+                password: decoder.string()?,
+                priority: 0,
+                static_ip: None,
+            };
+            let mut storage = Self{ networks: Vec::new(), wifi_finish: decoder.bool()? };
+            if !network.ssid.is_empty() {
+                let _ = storage.networks.push(network);
+            }
+            return Some(storage);
+        }
+        Some(Self{
+            networks: decoder.list()?,
+            wifi_finish: decoder.bool()?,
+        })
+    }
+}
 
 #[derive(Debug,Default)]
 pub struct WeatherStorage{
     token:heapless::String<64>
 }
 
-const OTHER_STORAGE_OFFSET:usize = WEATHER_STORAGE_OFFSET + size_of::<WeatherStorage>();
+impl Encode for WeatherStorage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.token);
+    }
 
-#[derive(Debug,Default)]
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            token: decoder.string()?,
+        })
+    }
+}
+
+#[derive(Debug,Default,Clone)]
 pub struct OtherStorage{
-    token:heapless::String<64>
+    pub token:heapless::String<64>,
+    pub sync_url:heapless::String<128>,//计时记录上传地址，为空时不上传
+    pub device_name:heapless::String<DEVICE_NAME_MAX>,//局域网中的名称，<设备名>.local，为空时使用默认名称
 }
-const TIMER_LOG_OFFSET:usize = OTHER_STORAGE_OFFSET + size_of::<OtherStorage>();
 
+impl OtherStorage {
+    pub fn device_name(&self)->&str{
+        if self.device_name.is_empty() { DEFAULT_DEVICE_NAME } else { self.device_name.as_str() }
+    }
+}
+
+impl Encode for OtherStorage {
+    //1: 上传地址和 token
+    //2: 增加设备名
+    const SCHEMA:u16 = 2;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.token);
+        encoder.str(&self.sync_url);
+        encoder.str(&self.device_name);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            token: decoder.string()?,
+            sync_url: decoder.string()?,
+            device_name: if decoder.schema >= 2 { decoder.string()? } else { heapless::String::new() },
+        })
+    }
+}
+
+pub const TIMER_LOG_SLOTS:usize = TIMER_LOG_AREA_SIZE / TIMER_LOG_RECORD_SIZE;
+const TIMER_LOG_SECTOR_SLOTS:usize = SECTOR_SIZE / TIMER_LOG_RECORD_SIZE;
+//timer_states 中每个槽位的状态
+pub const TIMER_LOG_EMPTY:u8 = 0;
+pub const TIMER_LOG_PENDING:u8 = 1;
+pub const TIMER_LOG_SYNCED:u8 = 2;
+
+//计时记录区在内存中的状态，启动时由 scan 从 flash 恢复
 #[derive(Debug)]
 pub struct TimerLogStateStorage{
-    timer_states:[u8;100], //标识是否有记录，记录是否同步
-    cursor: u8,//游标，与timer_states 长度对应
+    pub timer_states:[u8;TIMER_LOG_SLOTS], //标识是否有记录，记录是否同步
+    pub cursor: u8,//下一条记录写入的槽位
+    sequence:u32,//下一条记录的序号
+}
+
+impl Default for TimerLogStateStorage {
+    fn default() -> Self {
+        Self{
+            timer_states: [TIMER_LOG_EMPTY;TIMER_LOG_SLOTS],
+            cursor: 0,
+            sequence: 0,
+        }
+    }
 }
 
 impl TimerLogStateStorage{
-    //增加一个记录,并移动游标
-    fn add_log(){
+    //读取所有槽位，序号最大的是最新的记录，游标指向它的下一个槽位
+    pub fn scan()->Result<Self,StorageError>{
+        let mut state = Self::default();
+        let mut newest:Option<(u32,usize)> = None;
+        for index in 0..TIMER_LOG_SLOTS {
+            let buffer = read_slot(index)?;
+            let Some((sequence,synced)) = slot_header(&buffer) else { continue; };
+            if unpack::<TimerLog>(&buffer[TIMER_LOG_HEADER_SIZE..]).is_err() {
+                continue;
+            }
+            state.timer_states[index] = if synced { TIMER_LOG_SYNCED } else { TIMER_LOG_PENDING };
+            if newest.map_or(true, |(v,_)| sequence > v) {
+                newest = Some((sequence,index));
+            }
+        }
+        if let Some((sequence,index)) = newest {
+            state.cursor = ((index + 1) % TIMER_LOG_SLOTS) as u8;
+            state.sequence = sequence.wrapping_add(1);
+        }
+        Ok(state)
+    }
+
+    //在游标处追加一个记录，游标走到末尾后回到开头，进入扇区时擦除整个扇区覆盖最旧的记录
+    pub fn add_log(&mut self,log:&TimerLog)-> Result<(), StorageError>{
+        let mut buffer = [0xFFu8; TIMER_LOG_RECORD_SIZE];
+        pack(log, &mut buffer[TIMER_LOG_HEADER_SIZE..]).ok_or(StorageError::TooLarge)?;
+        buffer[..4].copy_from_slice(&self.sequence.to_le_bytes());
+        loop {
+            let index = self.cursor as usize % TIMER_LOG_SLOTS;
+            if index % TIMER_LOG_SECTOR_SLOTS == 0 {
+                //上传一直失败时最旧的记录会被覆盖
+                let pending = self.timer_states[index..index + TIMER_LOG_SECTOR_SLOTS].iter()
+                    .filter(|v| **v == TIMER_LOG_PENDING).count();
+                if pending > 0 {
+                    println!("timer log: drop {} unsynced records",pending);
+                }
+                let addr = timer_log_addr(index) as u32;
+                erase_flash(addr, addr + SECTOR_SIZE as u32)?;
+                self.timer_states[index..index + TIMER_LOG_SECTOR_SLOTS].fill(TIMER_LOG_EMPTY);
+            } else if read_slot(index)?.iter().any(|v| *v != 0xFF) {
+                //写入中途断电留下的槽位，跳过
+                self.cursor = ((index + 1) % TIMER_LOG_SLOTS) as u8;
+                continue;
+            }
+            write_flash(timer_log_addr(index) as u32, &buffer)?;
+            self.timer_states[index] = TIMER_LOG_PENDING;
+            self.cursor = ((index + 1) % TIMER_LOG_SLOTS) as u8;
+            self.sequence = self.sequence.wrapping_add(1);
+            return Ok(());
+        }
+    }
+
+    //读取某个槽位的记录，槽位为空或校验失败时返回 None
+    pub fn read_log(&self,index:usize)-> Option<TimerLog>{
+        if index >= TIMER_LOG_SLOTS || self.timer_states[index] == TIMER_LOG_EMPTY {
+            return None;
+        }
+        let result = read_slot(index)
+            .and_then(|buffer| Ok(unpack(&buffer[TIMER_LOG_HEADER_SIZE..])?));
+        match result {
+            Ok(log) => { Some(log) }
+            Err(e) => {
+                println!("read timer log fail：{:?}",e);
+                None
+            }
+        }
+    }
+
+    //遍历所有标识数组，返回待同步记录的槽位，从最旧的开始
+    pub fn pending_logs(&self)-> Vec<usize,TIMER_LOG_SLOTS>{
+        let mut result = Vec::new();
+        for i in 0..TIMER_LOG_SLOTS {
+            let index = (self.cursor as usize + i) % TIMER_LOG_SLOTS;
+            if self.timer_states[index] == TIMER_LOG_PENDING {
+                let _ = result.push(index);
+            }
+        }
+        result
+    }
+
+    //服务器确认后把槽位中的同步标记写为 0，不需要擦除
+    pub fn mark_synced(&mut self,index:usize)-> Result<(), StorageError>{
+        if self.timer_states[index] != TIMER_LOG_PENDING {
+            return Ok(());
+        }
+        write_flash((timer_log_addr(index) + TIMER_LOG_SYNCED_OFFSET) as u32, &[0u8;4])?;
+        self.timer_states[index] = TIMER_LOG_SYNCED;
+        Ok(())
+    }
+
+}
+
+fn timer_log_addr(index:usize)->usize{
+    TIMER_LOG_DATA_OFFSET + index * TIMER_LOG_RECORD_SIZE
+}
+
+fn read_slot(index:usize)->Result<[u8;TIMER_LOG_RECORD_SIZE],StorageError>{
+    let mut buffer = [0u8; TIMER_LOG_RECORD_SIZE];
+    read_flash(timer_log_addr(index) as u32, &mut buffer)?;
+    Ok(buffer)
+}
+
+//槽位的序号与是否已同步，空槽位返回 None
+fn slot_header(buffer:&[u8])->Option<(u32,bool)>{
+    let sequence = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
+    let synced = u32::from_le_bytes(buffer[TIMER_LOG_SYNCED_OFFSET..TIMER_LOG_SYNCED_OFFSET + 4].try_into().unwrap());
+    if sequence == u32::MAX {
+        return None;
+    }
+    Some((sequence,synced != u32::MAX))
+}
+
+//清空所有计时记录
+fn clear_timer_logs()->Result<(),StorageError>{
+    let addr = TIMER_LOG_DATA_OFFSET as u32;
+    erase_flash(addr, addr + TIMER_LOG_AREA_SIZE as u32)?;
+    Ok(())
+}
+
+impl Encode for TimerLog {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.bool(self.is_sync);
+        encoder.u8(self.finish_type as u8);
+        encoder.u64(self.begin_timestamp);
+        encoder.u64(self.end_timestamp);
+        encoder.u64(self.interval);
+        encoder.u8(self.work_type);
+        encoder.u32(self.paused_secs);
+        encoder.u16(self.pause_count);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            is_sync: decoder.bool()?,
+            finish_type: match decoder.u8()? {
+                0 => FinishType::Success,
+                1 => FinishType::Fail,
+                2 => FinishType::Abort,
+                _ => return None,
+            },
+            begin_timestamp: decoder.u64()?,
+            end_timestamp: decoder.u64()?,
+            interval: decoder.u64()?,
+            work_type: decoder.u8()?,
+            paused_secs: decoder.u32()?,
+            pause_count: decoder.u16()?,
+        })
+    }
+}
+
+//番茄钟配置，单位秒，cycles 为长休息前的工作轮数
+#[derive(Debug,Clone,Copy)]
+pub struct PomodoroStorage{
+    pub work_secs:u32,
+    pub short_break_secs:u32,
+    pub long_break_secs:u32,
+    pub cycles:u32,
+}
+
+impl Default for PomodoroStorage {
+    fn default() -> Self {
+        Self{
+            work_secs: 25 * 60,
+            short_break_secs: 5 * 60,
+            long_break_secs: 15 * 60,
+            cycles: 4,
+        }
+    }
+}
+
+impl PomodoroStorage {
+    pub fn is_valid(&self)->bool{
+        const MAX_SECS:u32 = 3600 * 2;
+        self.work_secs > 0 && self.work_secs <= MAX_SECS
+            && self.short_break_secs > 0 && self.short_break_secs <= MAX_SECS
+            && self.long_break_secs > 0 && self.long_break_secs <= MAX_SECS
+            && self.cycles > 0 && self.cycles <= 10
+    }
+}
+
+impl Encode for PomodoroStorage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u32(self.work_secs);
+        encoder.u32(self.short_break_secs);
+        encoder.u32(self.long_break_secs);
+        encoder.u32(self.cycles);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            work_secs: decoder.u32()?,
+            short_break_secs: decoder.u32()?,
+            long_break_secs: decoder.u32()?,
+            cycles: decoder.u32()?,
+        })
+    }
+}
+
+pub const WORK_ITEM_MAX:usize = 10;
+
+//一个计时类别，计时记录中保存的是 id，修改列表后仍然对应原来的类别
+#[derive(Debug,Clone)]
+pub struct WorkItemEntry{
+    pub id:u8,
+    pub name:heapless::String<20>,
+}
+
+//计时类别列表，默认类别的 id 与 WorkItem 的值一致
+#[derive(Debug)]
+pub struct WorkItemStorage{
+    pub items:Vec<WorkItemEntry,WORK_ITEM_MAX>,
+    next_id:u8,//下一个新类别的 id，删除的类别的 id 不马上重复使用
+}
+
+impl Default for WorkItemStorage {
+    fn default() -> Self {
+        let mut items = Vec::new();
+        for item in WorkItem::ALL.iter() {
+            let _ = items.push(WorkItemEntry{ id: *item as u8, name: heapless::String::from_str(item.title()).unwrap() });
+        }
+        let next_id = items.iter().map(|v| v.id).max().unwrap_or(0) + 1;
+        Self{ items, next_id }
+    }
+}
+
+impl WorkItemStorage {
+    pub fn name(&self,id:u8)->Option<&str>{
+        if id == 0 {
+            return None;
+        }
+        self.items.iter().find(|v| v.id == id).map(|v| v.name.as_str())
+    }
+
+    //按新的名称列表生成类别，同名的保留原来的 id，新的名称分配新的 id，重复的名称只保留一个
+    //名称过长或超过数量时返回 None
+    pub fn with_names<'a>(&self,names:impl Iterator<Item=&'a str>)->Option<Self>{
+        let mut result = Self{ items: Vec::new(), next_id: self.next_id };
+        for name in names {
+            if result.items.iter().any(|v| v.name == name) {
+                continue;
+            }
+            let id = match self.items.iter().find(|v| v.name == name) {
+                Some(v) => v.id,
+                None => result.allocate_id(&self.items)?,
+            };
+            result.items.push(WorkItemEntry{ id, name: heapless::String::from_str(name).ok()? }).ok()?;
+        }
+        Some(result)
+    }
 
+    //依次递增，用完后回到 1，跳过新旧列表中正在使用的 id
+    fn allocate_id(&mut self,old:&[WorkItemEntry])->Option<u8>{
+        for _ in 0..u8::MAX {
+            let id = self.next_id.max(1);
+            self.next_id = id.checked_add(1).unwrap_or(1);
+            if !self.items.iter().chain(old.iter()).any(|v| v.id == id) {
+                return Some(id);
+            }
+        }
+        None
+    }
+}
+
+impl Encode for WorkItemStorage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u8(self.items.len() as u8);
+        for item in self.items.iter() {
+            encoder.u8(item.id);
+            encoder.str(&item.name);
+        }
+        encoder.u8(self.next_id);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let len = decoder.u8()? as usize;
+        if len > WORK_ITEM_MAX {
+            return None;
+        }
+        let mut items = Vec::new();
+        for _ in 0..len {
+            items.push(WorkItemEntry{ id: decoder.u8()?, name: decoder.string()? }).ok()?;
+        }
+        Some(Self{ items, next_id: decoder.u8()? })
+    }
+}
+
+pub const ALARM_MAX:usize = 8;
+
+//闹钟列表
+#[derive(Debug,Default,Clone)]
+pub struct AlarmStorage{
+    pub alarms:Vec<Alarm,ALARM_MAX>,
+    pub next_id:u8,
+}
+
+impl AlarmStorage {
+    pub fn is_valid(&self)->bool{
+        self.alarms.iter().enumerate().all(|(index,v)| {
+            v.is_valid() && v.id != 0 && !self.alarms[..index].iter().any(|other| other.id == v.id)
+        })
     }
 
+    pub fn get(&self,id:u8)->Option<&Alarm>{
+        self.alarms.iter().find(|v| v.id == id)
+    }
 
-    //同步记录,遍历所有标识数组，同步待同步记录至服务器
-    fn sync(){
+    //新增一个工作日 8 点的闹钟，返回序号
+    pub fn add(&mut self)->Option<usize>{
+        if self.alarms.is_full() {
+            return None;
+        }
+        let alarm = Alarm{
+            id: self.allocate_id()?,
+            enabled: true,
+            hour: 8,
+            minute: 0,
+            weekdays: 0x1F,
+            sound: 0,
+            label: self.default_label(),
+        };
+        self.alarms.push(alarm).ok()?;
+        Some(self.alarms.len() - 1)
+    }
 
+    //在默认名称和常用名称之间切换，step 为 1 或 -1
+    pub fn change_label(&mut self,index:usize,step:i32){
+        let Some(alarm) = self.alarms.get(index) else { return; };
+        let count = ALARM_LABELS.len() as i32 + 1;
+        let current = ALARM_LABELS.iter().position(|v| *v == alarm.label.as_str()).map_or(0, |v| v as i32 + 1);
+        let next = (current + step).rem_euclid(count) as usize;
+        let label = if next == 0 {
+            self.default_label()
+        }else{
+            heapless::String::from_str(ALARM_LABELS[next - 1]).unwrap_or_default()
+        };
+        self.alarms[index].label = label;
     }
 
+    //没有被其他闹钟使用的最小编号
+    fn default_label(&self)->heapless::String<20>{
+        for number in 1..=ALARM_MAX + 1 {
+            let mut label = heapless::String::new();
+            let _ = write!(label,"闹钟{}",number);
+            if !self.alarms.iter().any(|v| v.label == label) {
+                return label;
+            }
+        }
+        heapless::String::new()
+    }
+
+    //依次递增，用完后回到 1，跳过正在使用的 id
+    fn allocate_id(&mut self)->Option<u8>{
+        for _ in 0..u8::MAX {
+            let id = self.next_id.max(1);
+            self.next_id = id.checked_add(1).unwrap_or(1);
+            if self.get(id).is_none() {
+                return Some(id);
+            }
+        }
+        None
+    }
 }
 
-const TIMER_LOG_END_OFFSET:usize = TIMER_LOG_OFFSET + size_of::<TimerLogStateStorage>();
+impl Encode for Alarm {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u8(self.id);
+        encoder.bool(self.enabled);
+        encoder.u8(self.hour);
+        encoder.u8(self.minute);
+        encoder.u8(self.weekdays);
+        encoder.u8(self.sound);
+        encoder.str(&self.label);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            id: if decoder.schema >= 2 { decoder.u8()? } else { 0 },
+            enabled: decoder.bool()?,
+            hour: decoder.u8()?,
+            minute: decoder.u8()?,
+            weekdays: decoder.u8()?,
+            sound: decoder.u8()?,
+            label: decoder.string()?,
+        })
+    }
+}
+
+impl Encode for AlarmStorage {
+    //1: 闹钟列表
+    //2: 闹钟增加 id
+    const SCHEMA:u16 = 2;
+
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(&self.alarms);
+        encoder.u8(self.next_id);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        let mut alarms:Vec<Alarm,ALARM_MAX> = decoder.list()?;
+        let next_id = if decoder.schema >= 2 {
+            decoder.u8()?
+        }else{
+            //旧数据没有 id，按顺序分配
+            for (index,alarm) in alarms.iter_mut().enumerate() {
+                alarm.id = index as u8 + 1;
+            }
+            alarms.len() as u8 + 1
+        };
+        Some(Self{ alarms, next_id })
+    }
+}
+
+pub const INTERVAL_MAX:usize = 4;
+
+//间歇训练序列
+#[derive(Debug)]
+pub struct IntervalStorage{
+    pub sequences:Vec<IntervalSequence,INTERVAL_MAX>,
+}
+
+impl Default for IntervalStorage {
+    fn default() -> Self {
+        let mut sequences = Vec::new();
+        if let Some(sequence) = IntervalSequence::parse("间歇跑：热身 5m; 8x(工作 40s, 休息 20s); 放松 3m") {
+            let _ = sequences.push(sequence);
+        }
+        Self{ sequences }
+    }
+}
+
+impl IntervalStorage {
+    pub fn is_valid(&self)->bool{
+        self.sequences.iter().all(|v| v.is_valid())
+    }
+}
+
+impl Encode for IntervalStep {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.name);
+        encoder.u32(self.secs);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            name: decoder.string()?,
+            secs: decoder.u32()?,
+        })
+    }
+}
+
+impl Encode for IntervalBlock {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.u8(self.repeat);
+        encoder.list(&self.steps);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            repeat: decoder.u8()?,
+            steps: decoder.list()?,
+        })
+    }
+}
+
+impl Encode for IntervalSequence {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.str(&self.name);
+        encoder.list(&self.blocks);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            name: decoder.string()?,
+            blocks: decoder.list()?,
+        })
+    }
+}
+
+impl Encode for IntervalStorage {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.list(&self.sequences);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Option<Self> {
+        Some(Self{
+            sequences: decoder.list()?,
+        })
+    }
+}
 
 
 // 为各个存储结构体实现 NvsStorage trait
-impl_storage!(VersionStorage, VERSION_STORAGE_OFFSET);
-impl_storage!(WifiStorage, WIFI_STORAGE_OFFSET);
-impl_storage!(WeatherStorage, WEATHER_STORAGE_OFFSET);
-impl_storage!(OtherStorage, OTHER_STORAGE_OFFSET);
+//简单的设置每个字段一个键，没有的键使用默认值，新增字段不需要迁移
+impl NvsStorage for VersionStorage {
+    fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+        Ok(Self{
+            version: required(kv.get_u32("version")?)?,
+            init_tag: required(kv.get_u32("init_tag")?)?,
+        })
+    }
+
+    fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+        kv.set_u32("version", self.version)?;
+        kv.set_u32("init_tag", self.init_tag)?;
+        Ok(())
+    }
+}
+
+//网络列表编码后保存在一个键中，"wifi.ssid"、"wifi.password" 是只有一个网络时的键，读取时转换
+impl NvsStorage for WifiStorage {
+    fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+        if let Some(data) = kv.get_blob("wifi.networks")? {
+            return Ok(unpack(&data)?);
+        }
+        let network = WifiNetwork{
+            ssid: kv.get_str("wifi.ssid")?.unwrap_or_default(),
+            password: kv.get_str("wifi.password")?.unwrap_or_default(),
+            priority: 0,
+            static_ip: None,
+        };
+        let mut storage = Self{ networks: Vec::new(), wifi_finish: kv.get_bool("wifi.finish")?.unwrap_or(false) };
+        if !network.ssid.is_empty() {
+            let _ = storage.networks.push(network);
+        }
+        Ok(storage)
+    }
+
+    fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+        let mut buffer = vec![0u8; 0x300];
+        let len = pack(self, &mut buffer).ok_or(StorageError::TooLarge)?;
+        kv.set_blob("wifi.networks", &buffer[..len])?;
+        for key in ["wifi.ssid", "wifi.password", "wifi.finish"] {
+            if kv.contains(key)? {
+                kv.remove(key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NvsStorage for WeatherStorage {
+    fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+        Ok(Self{
+            token: kv.get_str("weather.token")?.unwrap_or_default(),
+        })
+    }
+
+    fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+        kv.set_str("weather.token", &self.token)?;
+        Ok(())
+    }
+}
+
+impl NvsStorage for OtherStorage {
+    fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+        Ok(Self{
+            token: kv.get_str("other.token")?.unwrap_or_default(),
+            sync_url: kv.get_str("other.sync_url")?.unwrap_or_default(),
+            device_name: kv.get_str("other.device_name")?.unwrap_or_default(),
+        })
+    }
+
+    fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+        kv.set_str("other.token", &self.token)?;
+        kv.set_str("other.sync_url", &self.sync_url)?;
+        kv.set_str("other.device_name", &self.device_name)?;
+        Ok(())
+    }
+}
+
+impl NvsStorage for PomodoroStorage {
+    fn load(kv:&mut Kv) -> Result<Self,StorageError> {
+        let default = Self::default();
+        Ok(Self{
+            work_secs: kv.get_u32("pomodoro.work")?.unwrap_or(default.work_secs),
+            short_break_secs: kv.get_u32("pomodoro.short_break")?.unwrap_or(default.short_break_secs),
+            long_break_secs: kv.get_u32("pomodoro.long_break")?.unwrap_or(default.long_break_secs),
+            cycles: kv.get_u32("pomodoro.cycles")?.unwrap_or(default.cycles),
+        })
+    }
+
+    fn save(&self,kv:&mut Kv) -> Result<(), StorageError> {
+        kv.set_u32("pomodoro.work", self.work_secs)?;
+        kv.set_u32("pomodoro.short_break", self.short_break_secs)?;
+        kv.set_u32("pomodoro.long_break", self.long_break_secs)?;
+        kv.set_u32("pomodoro.cycles", self.cycles)?;
+        Ok(())
+    }
+}
+
+impl_storage!(WorkItemStorage, "work_items", 0x100);
+impl_storage!(AlarmStorage, "alarms", 0x200);
+impl_storage!(IntervalStorage, "intervals", 0x900);
+
+//版本 1 的格式，直接把结构体内存写入 flash，位置由各结构体大小依次累加
+//只用于升级时读取旧数据，结构体必须与当时的定义保持一致，不要修改
+mod legacy {
+    use alloc::vec;
+    use core::mem::size_of;
+    use core::ptr;
+    use core::str::FromStr;
+    use esp_storage::{FlashStorage, FlashStorageError};
+    use super::{NVS_OFFSET, read_flash};
+
+    pub struct VersionStorage{
+        pub version:u32,
+        pub init_tag:u32,
+    }
+
+    pub struct WifiStorage{
+        pub wifi_ssid:heapless::String<32>,
+        pub wifi_password:heapless::String<64>,
+        pub wifi_finish:bool
+    }
+
+    pub struct WeatherStorage{
+        pub token:heapless::String<64>
+    }
+
+    pub struct OtherStorage{
+        pub token:heapless::String<64>,
+    }
+
+    pub const VERSION_STORAGE_OFFSET:usize = NVS_OFFSET;
+    pub const WIFI_STORAGE_OFFSET:usize = VERSION_STORAGE_OFFSET + size_of::<VersionStorage>();
+    pub const WEATHER_STORAGE_OFFSET:usize = WIFI_STORAGE_OFFSET + size_of::<WifiStorage>();
+    pub const OTHER_STORAGE_OFFSET:usize = WEATHER_STORAGE_OFFSET + size_of::<WeatherStorage>();
+
+    pub fn read<T>(offset:usize)->Result<T,FlashStorageError>{
+        let mut buffer = vec![0u8; size_of::<T>()];
+        read_flash(offset as u32, &mut buffer)?;
+        Ok(unsafe { ptr::read_unaligned(buffer.as_ptr() as *const T) })
+    }
+
+    //内存直接读出的字符串长度可能是乱的，先检查长度再取内容
+    pub fn text<const N:usize>(text:&heapless::String<N>)->Option<heapless::String<N>>{
+        if text.len() > N {
+            return None;
+        }
+        let text = core::str::from_utf8(text.as_bytes()).ok()?;
+        heapless::String::from_str(text).ok()
+    }
+}
 
 
 pub static WIFI_INFO:Mutex<CriticalSectionRawMutex,Option<WifiStorage>>  =  Mutex::new(None);
 pub static WEATHER_API:Mutex<CriticalSectionRawMutex,Option<WeatherStorage>>  =  Mutex::new(None);
+pub static TIMER_LOG_STATE:Mutex<CriticalSectionRawMutex,Option<TimerLogStateStorage>>  =  Mutex::new(None);
+pub static OTHER_INFO:Mutex<CriticalSectionRawMutex,Option<OtherStorage>>  =  Mutex::new(None);
+pub static POMODORO_INFO:Mutex<CriticalSectionRawMutex,Option<PomodoroStorage>>  =  Mutex::new(None);
+pub static WORK_ITEM_INFO:Mutex<CriticalSectionRawMutex,Option<WorkItemStorage>>  =  Mutex::new(None);
+pub static ALARM_INFO:Mutex<CriticalSectionRawMutex,Option<AlarmStorage>>  =  Mutex::new(None);
+pub static INTERVAL_INFO:Mutex<CriticalSectionRawMutex,Option<IntervalStorage>>  =  Mutex::new(None);
+//有新记录或上传配置变化时通知上传任务
+pub static TIMER_LOG_SYNC_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+//读取记录，没有或校验失败时使用默认值
+fn load_or_default<T:NvsStorage + Default>(kv:&mut Option<Kv>,name:&str)->T{
+    let Some(kv) = kv.as_mut() else { return T::default(); };
+    match T::load(kv) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("read {} storage fail：{:?}",name,e);
+            T::default()
+        }
+    }
+}
+
+//flash 中保存的数据版本，没有数据时返回 None，键值存储未初始化时检查旧格式
+fn stored_version(kv:&mut Kv)->Result<Option<u32>,StorageError>{
+    if kv.is_formatted() {
+        return Ok(VersionStorage::load(kv).ok().filter(|v| v.init_tag == INIT_TAG).map(|v| v.version));
+    }
+    match legacy::read::<legacy::VersionStorage>(legacy::VERSION_STORAGE_OFFSET)? {
+        v if v.init_tag == INIT_TAG => Ok(Some(v.version)),
+        _ => Ok(None),
+    }
+}
 
 pub async fn enter_process(){
-    let version_storage = VersionStorage::read();
-    match version_storage {
-        Ok(v) => {
-            if v.init_tag  != INIT_TAG {
-                init_storage_area();
-            }
+    let mut kv = match open_kv() {
+        Ok(kv) => Some(kv),
+        Err(e) => {
+            //读取出错时不能当作空白 flash 清除，只使用默认值
+            println!("open storage fail：{:?}",e);
+            None
+        }
+    };
+    if let Some(kv) = kv.as_mut() {
+        let result = match stored_version(kv) {
+            Ok(Some(version)) if version < STORAGE_VERSION => migrate(kv, version),
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => format_kv(kv),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            println!("prepare storage fail：{:?}",e);
+        }
+    }
+
+    WIFI_INFO.lock().await.replace(load_or_default(&mut kv,"wifi"));
+    WEATHER_API.lock().await.replace(load_or_default(&mut kv,"weather"));
+    OTHER_INFO.lock().await.replace(load_or_default(&mut kv,"other"));
+
+    let pomodoro:PomodoroStorage = load_or_default(&mut kv,"pomodoro");
+    POMODORO_INFO.lock().await.replace(if pomodoro.is_valid() { pomodoro } else { PomodoroStorage::default() });
+
+    let work_items:WorkItemStorage = load_or_default(&mut kv,"work item");
+    WORK_ITEM_INFO.lock().await.replace(if work_items.items.is_empty() { WorkItemStorage::default() } else { work_items });
+
+    let alarms:AlarmStorage = load_or_default(&mut kv,"alarm");
+    ALARM_INFO.lock().await.replace(if alarms.is_valid() { alarms } else { AlarmStorage::default() });
+
+    let intervals:IntervalStorage = load_or_default(&mut kv,"interval");
+    INTERVAL_INFO.lock().await.replace(if intervals.is_valid() { intervals } else { IntervalStorage::default() });
+
+    let timer_log_state = TimerLogStateStorage::scan().unwrap_or_else(|e| {
+        println!("read timer log state fail：{:?}",e);
+        TimerLogStateStorage::default()
+    });
+    TIMER_LOG_STATE.lock().await.replace(timer_log_state);
+}
+
+//键值存储中当前不使用的一半，都无效时使用第二半，第一半可能还有版本 1 的数据
+fn inactive_half(kv:&Kv)->usize{
+    match kv.active_half() {
+        Some(0) => 1,
+        Some(_) => 0,
+        None => 1,
+    }
+}
+
+//清空另一半并只写入版本号，其它设置读取时使用默认值，计时记录一起清空
+fn format_kv(kv:&mut Kv)->Result<(),StorageError>{
+    kv.begin_format(inactive_half(kv))?;
+    clear_timer_logs()?;
+    VersionStorage{ version: STORAGE_VERSION, init_tag: INIT_TAG }.save(kv)?;
+    kv.commit_format()?;
+    Ok(())
+}
 
-            let wifi = WifiStorage::read().unwrap();
-            WIFI_INFO.lock().await.replace(wifi);
+//固件升级后按版本迁移，版本 1 的数据在 NVS 开头，也就是键值存储的第一半中
+//写入键值存储的另一半，写入头部前这一半无效，中途断电下次启动会重新迁移
+fn migrate(kv:&mut Kv,from:u32)->Result<(),StorageError>{
+    println!("storage migrate from version {}",from);
+    kv.begin_format(inactive_half(kv))?;
+    if from == 1 {
+        migrate_v1(kv)?;
+    }
+    VersionStorage{ version: STORAGE_VERSION, init_tag: INIT_TAG }.save(kv)?;
+    kv.commit_format()?;
+    Ok(())
+}
+
+//从结构体内存格式迁移，保留 Wifi、天气和 token，版本 1 没有上传地址
+//旧版本中计时记录等的位置随结构体大小变化过，无法可靠读取，直接清空
+fn migrate_v1(kv:&mut Kv)->Result<(),StorageError>{
+    clear_timer_logs()?;
+    let wifi = legacy::read::<legacy::WifiStorage>(legacy::WIFI_STORAGE_OFFSET)?;
+    let weather = legacy::read::<legacy::WeatherStorage>(legacy::WEATHER_STORAGE_OFFSET)?;
+    let other = legacy::read::<legacy::OtherStorage>(legacy::OTHER_STORAGE_OFFSET)?;
+
+    if let (Some(wifi_ssid),Some(wifi_password)) = (legacy::text(&wifi.wifi_ssid),legacy::text(&wifi.wifi_password)) {
+        let mut storage = WifiStorage{ networks: Vec::new(), wifi_finish: wifi.wifi_finish };
+        if !wifi_ssid.is_empty() {
+            storage.add_network(WifiNetwork{ ssid: wifi_ssid, password: wifi_password, priority: 0, static_ip: None });
+            storage.wifi_finish = wifi.wifi_finish;
         }
-        Err(_) => {
-            init_storage_area();
+        storage.save(kv)?;
+    }
+    WeatherStorage{ token: legacy::text(&weather.token).unwrap_or_default() }.save(kv)?;
+    OtherStorage{
+        token: legacy::text(&other.token).unwrap_or_default(),
+        sync_url: heapless::String::new(),
+        device_name: heapless::String::new(),
+    }.save(kv)?;
+    Ok(())
+}
+
+//保存一条计时记录
+pub async fn save_timer_log(log:&TimerLog){
+    if let Some(state) = TIMER_LOG_STATE.lock().await.as_mut() {
+        match state.add_log(log) {
+            Ok(_) => {
+                println!("timer log saved:{:?}",log);
+                TIMER_LOG_SYNC_SIGNAL.signal(());
+            }
+            Err(e) => { println!("timer log save fail：{:?}",e); }
         }
     }
 }
 
+//恢复默认设置，写入完成前原来的设置仍然有效
 pub fn init_storage_area(){
-    let mut version =  VersionStorage::default();
-    version.version = 1;
-    version.init_tag = INIT_TAG;
-    version.write();
-
-    let mut wifi =  WifiStorage::default();
-    wifi.wifi_finish = false;
-    wifi.write();
-
-    WeatherStorage::default().write();
-    OtherStorage::default().write();
-}
\ No newline at end of file
+    if let Err(e) = open_kv().and_then(|mut kv| format_kv(&mut kv)) {
+        println!("init storage fail：{:?}",e);
+    }
+}
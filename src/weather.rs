@@ -5,7 +5,7 @@ use esp_println::println;
 use crate::make_static;
 use crate::model::seniverse::{DailyResult, form_json};
 use crate::request::RequestClient;
-use crate::wifi::{finish_wifi, use_wifi};
+use crate::wifi::use_wifi;
 
 
 
@@ -24,12 +24,12 @@ impl Weather {
         let stack = use_wifi().await;
         if let Ok(v) = stack {
             println!("请求 stack 成功");
-            let mut request = RequestClient::new(v).await;
+            let mut request = RequestClient::new(v.stack()).await;
             println!("开始请求成功");
             let result = request.send_request("http://api.seniverse.com/v3/weather/daily.json?key=SvRIiZPU5oGiqcHc1&location=wuhan&language=zh-Hans&unit=c&start=0&days=5").await;
+            drop(v);
             match result {
                 Ok(response) => {
-                    finish_wifi().await;
                     let mut daily_result = form_json(&response.data[..response.length]);
                     if let Some(mut v) =  daily_result {
                         self.daily_result.lock().await.replace(v.results.pop().unwrap());
@@ -38,7 +38,6 @@ impl Weather {
                     Ok(())
                 }
                 Err(e) => {
-                    finish_wifi().await;
                     println!("请求失败{:?}",e);
                     Err(())
                 }
@@ -1,18 +1,34 @@
-use core::str::{from_utf8, FromStr};
+use alloc::boxed::Box;
+use alloc::format;
+use core::net::Ipv4Addr;
+use core::str::FromStr;
 use embassy_futures::select::{Either, select};
 use embassy_net::{IpListenEndpoint, Stack};
 use embassy_net::tcp::TcpSocket;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::mutex::Mutex;
 use embassy_sync::signal::Signal;
 use embassy_time::{Duration, Timer};
-use esp_println::{print, println};
+use esp_println::println;
 use esp_wifi::wifi::WifiDevice;
 use hal::reset::software_reset;
-use heapless::Vec;
-use crate::wifi::{AP_STACK_MUT, finish_wifi,  use_wifi, WIFI_MODEL, WifiModel};
-use crate::storage::{NvsStorage, WIFI_INFO};
+use heapless::{String, Vec};
+use crate::http::{dispatch, Form, HandlerFuture, HEADER_MAX, json_string, ParseError, Request, Response, Route, Status};
+use crate::wifi::{AP_STACK_MUT, IP_ADDRESS, MDNS_UPDATE_SIGNAL, scan_networks, use_wifi, WIFI_MODEL, WifiModel};
+use crate::mdns::is_valid_name;
+use crate::interval::IntervalSequence;
+use crate::storage::{INTERVAL_INFO, IntervalStorage, NvsStorage, OTHER_INFO, OtherStorage, POMODORO_INFO, PomodoroStorage, TIMER_LOG_SYNC_SIGNAL, StaticIp, WIFI_INFO, WifiNetwork, WORK_ITEM_INFO, WORK_ITEM_MAX};
 
 pub static STOP_WEB_SERVICE: Signal<CriticalSectionRawMutex,()> = Signal::new();
+//网页配置服务是否在监听，mDNS 只在监听期间发布服务
+pub static WEB_SERVICE_RUNNING:Mutex<CriticalSectionRawMutex,bool> = Mutex::new(false);
+
+//更新监听状态并通知 mDNS 重新发布或撤销服务
+async fn set_running(running:bool){
+    *WEB_SERVICE_RUNNING.lock().await = running;
+    MDNS_UPDATE_SIGNAL.signal(());
+}
+
 #[embassy_executor::task]
 pub async fn web_service(){
     match WIFI_MODEL.lock().await.unwrap() {
@@ -24,24 +40,51 @@ pub async fn web_service(){
             }
         }
         WifiModel::STA => {
-            unsafe {
-                loop {
-                    match use_wifi().await {
-                        Ok(stack) => {
-                            web_tcp_socket(stack).await;
-                            finish_wifi().await;
-                            break;
-                        }
-                        Err(_) => {}
+            //服务运行期间一直持有网络，不会被 do_stop 关闭
+            loop {
+                match use_wifi().await {
+                    Ok(lease) => {
+                        web_tcp_socket(lease.stack()).await;
+                        break;
                     }
-                    Timer::after(Duration::from_millis(100));
+                    Err(_) => {}
                 }
+                Timer::after(Duration::from_millis(100)).await;
             }
         }
     }
 
 }
 
+//请求头和请求体一起读入，按 Content-Length 读完请求体，超出时返回 413
+const REQUEST_MAX:usize = 4096;
+
+//路由表，按顺序匹配，路径以 * 结尾时匹配前缀
+static ROUTES:&[Route] = &[
+    Route::new("GET", "/", config_page),
+    Route::new("GET", "/config", config_page),
+    Route::new("GET", "/api/scan", api_scan),
+    Route::new("GET", "/wifi_networks", wifi_networks),
+    Route::new("GET", "/work_items", work_items),
+    //各系统连接 wifi 后检测是否能上网的地址，返回跳转时会弹出登录页面
+    Route::new("GET", "/generate_204", connectivity_check),//Android
+    Route::new("GET", "/gen_204", connectivity_check),
+    Route::new("GET", "/hotspot-detect.html", connectivity_check),//iOS、macOS
+    Route::new("GET", "/library/test/success.html", connectivity_check),
+    Route::new("GET", "/connecttest.txt", connectivity_check),//Windows
+    Route::new("GET", "/ncsi.txt", connectivity_check),
+    Route::new("GET", "/redirect", connectivity_check),
+    Route::new("GET", "/canonical.html", connectivity_check),//Firefox
+    Route::new("GET", "/success.txt", connectivity_check),
+    Route::new("POST", "/configure_wifi", configure_wifi),
+    Route::new("POST", "/remove_wifi", remove_wifi),
+    Route::new("POST", "/configure_pomodoro", configure_pomodoro),
+    Route::new("POST", "/configure_work_items", configure_work_items),
+    Route::new("POST", "/configure_intervals", configure_intervals),
+    Route::new("POST", "/configure_sync", configure_sync),
+    Route::new("POST", "/configure_device", configure_device),
+];
+
 async fn  web_tcp_socket<D: esp_wifi::wifi::WifiDeviceMode> (stack:&Stack<WifiDevice<'_,D>>){
 
     let mut rx_buffer = [0; 1536];
@@ -49,18 +92,20 @@ async fn  web_tcp_socket<D: esp_wifi::wifi::WifiDeviceMode> (stack:&Stack<WifiDe
     //网页配置服务
     let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
     socket.set_timeout(Some(embassy_time::Duration::from_secs(10)));
+    set_running(true).await;
     loop {
         println!("Wait for connection...");
         let wait_stop = STOP_WEB_SERVICE.wait();
         let r = socket
             .accept(IpListenEndpoint {
                 addr: None,
-                port: 8080,
+                port: 80,
             })
             ;
         match select(wait_stop,r).await{
             Either::First(_) => {
                 STOP_WEB_SERVICE.reset();
+                set_running(false).await;
                 break;
             }
             Either::Second(r) => {
@@ -74,38 +119,58 @@ async fn  web_tcp_socket<D: esp_wifi::wifi::WifiDeviceMode> (stack:&Stack<WifiDe
 
                 use embedded_io_async::Write;
 
-                let mut buffer = [0u8; 2048];
+                let mut buffer = [0u8; REQUEST_MAX];
                 let mut pos = 0;
-                loop {
-                    match socket.read(&mut buffer).await {
+                let response = loop {
+                    if pos == buffer.len() {
+                        break Some(Response::error(Status::PayloadTooLarge));
+                    }
+                    match socket.read(&mut buffer[pos..]).await {
                         Ok(0) => {
                             println!("read EOF");
-                            break;
+                            break None;
                         }
                         Ok(len) => {
-                            let to_print =
-                                unsafe { core::str::from_utf8_unchecked(&buffer[..(pos + len)]) };
-
-                            if to_print.contains("\r\n\r\n") {
-                                print!("{}", to_print);
-                                println!();
-
-                                process_http(&mut socket,to_print).await;
-                                break;
-                            }
-
                             pos += len;
+                            let mut headers = [httparse::EMPTY_HEADER; HEADER_MAX];
+                            match Request::parse(&buffer[..pos], &mut headers) {
+                                Ok(request) => {
+                                    if request.total_len() > REQUEST_MAX {
+                                        break Some(Response::error(Status::PayloadTooLarge));
+                                    }
+                                    //请求体还没读完
+                                    if !request.body_complete() {
+                                        continue;
+                                    }
+                                    println!("request: {} {} body {}", request.method, request.path, request.body.len());
+                                    break Some(dispatch(ROUTES, &request).await);
+                                }
+                                Err(ParseError::Partial) => {}
+                                Err(ParseError::Invalid) => {
+                                    break Some(Response::error(Status::BadRequest));
+                                }
+                            }
                         }
                         Err(e) => {
                             println!("read error: {:?}", e);
-                            break;
+                            break None;
                         }
                     };
-                }
+                };
 
-                let r = socket.flush().await;
-                if let Err(e) = r {
-                    println!("flush error: {:?}", e);
+                if let Some(response) = response {
+                    println!("response: {}", response.status().code());
+                    if let Err(e) = write_response(&mut socket, &response).await {
+                        println!("write error: {:?}", e);
+                    }
+                    let r = socket.flush().await;
+                    if let Err(e) = r {
+                        println!("flush error: {:?}", e);
+                    }
+                    if let Some(after) = response.after() {
+                        Timer::after(Duration::from_millis(1000)).await;
+                        after();
+                    }
                 }
                 Timer::after(Duration::from_millis(1000)).await;
 
@@ -119,152 +184,337 @@ async fn  web_tcp_socket<D: esp_wifi::wifi::WifiDeviceMode> (stack:&Stack<WifiDe
     }
 
 }
-async fn process_http(socket:&mut TcpSocket<'_>,buffer:&str) {
+
+async fn write_response(socket:&mut TcpSocket<'_>,response:&Response)->Result<(),embassy_net::tcp::Error>{
     use embedded_io_async::Write;
-    use heapless::String;
-
-    let mut headers = [httparse::EMPTY_HEADER; 64];
-    let mut req = httparse::Request::new(&mut headers);
-    req.parse(buffer.as_ref());
-    println!("request:{:?}", req);
-    if let Some("GET") = req.method {
-        if let Some("/config") = req.path {
-            let content = concat!("HTTP/1.0 200 OK\r\n\r\n", include_str!("../files/config.html"));
-            let r = socket
-                .write_all(
-                    content.as_bytes()
-                )
-                .await;
-
-            if let Err(e) = r {
-                println!("write error: {:?}", e);
-            }
-        }else{
-            //默认
-            let content = concat!("HTTP/1.0 200 OK\r\n\r\n", include_str!("../files/config.html"));
-            let r = socket
-                .write_all(
-                    content.as_bytes()
-                )
-                .await;
-
-            if let Err(e) = r {
-                println!("write error: {:?}", e);
+    socket.write_all(response.head().as_bytes()).await?;
+    socket.write_all(response.body_bytes()).await
+}
+
+//保存设置的接口都返回 {"success":..}
+fn result(success:bool)->Response{
+    Response::json(format!("{{\"success\":{}}}", success))
+}
+
+fn config_page<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        Response::html(include_str!("../files/config.html"))
+    })
+}
+
+//系统检测联网的请求跳转到配置页，手机连接热点后会自动弹出
+fn connectivity_check<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let ip = unsafe { &IP_ADDRESS };
+        Response::redirect(&format!("http://{}/config", ip))
+    })
+}
+
+//附近的网络，按信号强度排序
+fn api_scan<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut networks = scan_networks().await;
+        networks.sort_unstable_by(|a, b| b.signal_strength.cmp(&a.signal_strength));
+        let mut content = alloc::string::String::from("{\"networks\":[");
+        for (index, network) in networks.iter().enumerate() {
+            if index > 0 {
+                content.push(',');
             }
+            let auth = match network.auth_method {
+                Some(auth_method) => format!("{:?}", auth_method),
+                None => alloc::string::String::from("Unknown"),
+            };
+            content.push_str(&format!("{{\"ssid\":{},\"rssi\":{},\"auth\":{}}}", json_string(&network.ssid), network.signal_strength, json_string(&auth)));
         }
-    }
-    if let Some("POST") = req.method {
-        if let Some("/configure_wifi") = req.path {
-            let form_fields = parse_form(&req, buffer);
-            println!("form_data:{:?}", form_fields);
-
-            if let Ok(fields) = form_fields {
-                let mut ssid: Option<&str> = None;
-                let mut password: Option<&str> = None;
-
-                for field in fields {
-                    if field.0 == "ssid" {
-                        ssid = Some(field.1);
-                        println!("ssid:{}", field.1);
-                    } else if field.0 == "password" {
-                        password = Some(field.1);
-                        println!("password:{}", field.1);
-                    }
+        content.push_str("]}");
+        Response::json(content)
+    })
+}
+
+//已保存的网络，不返回密码
+fn wifi_networks<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut content = alloc::string::String::from("{\"networks\":[");
+        if let Some(wifi_info) = WIFI_INFO.lock().await.as_ref() {
+            for (index, network) in wifi_info.networks.iter().enumerate() {
+                if index > 0 {
+                    content.push(',');
                 }
+                let address = match network.static_ip {
+                    Some(static_ip) => json_string(&format!("{}/{}", Ipv4Addr::from(static_ip.address), static_ip.prefix_len)),
+                    None => alloc::string::String::from("null"),
+                };
+                content.push_str(&format!("{{\"ssid\":{},\"priority\":{},\"address\":{}}}", json_string(&network.ssid), network.priority, address));
+            }
+        }
+        content.push_str("]}");
+        Response::json(content)
+    })
+}
 
-                if let Some(wifi_info) = WIFI_INFO.lock().await.as_mut() {
-                    println!("wifi_info:{:?}", wifi_info);
-                    wifi_info.wifi_ssid = String::from_str(ssid.unwrap()).unwrap();
-                    wifi_info.wifi_password = String::from_str(password.unwrap()).unwrap();
-                    wifi_info.wifi_finish = true;
-                    match wifi_info.write() {
+//字段中有密码等信息，只打印解析错误
+fn parse_form(request:&Request)->Option<Form>{
+    Form::parse(request).map_err(|e| println!("form error: {:?}", e)).ok()
+}
+
+fn configure_wifi<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            //没有填地址时使用 DHCP，填了但格式不对时不保存
+            let static_ip = match form.get("address").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                Some(address) => StaticIp::parse(address, form.get("gateway").unwrap_or(""), form.get("dns").unwrap_or("")).map(Some),
+                None => Some(None),
+            };
+            let network = match (form.get("ssid").map(String::from_str), String::from_str(form.get("password").unwrap_or("")), form.get("priority").unwrap_or("0").parse::<u8>(), static_ip) {
+                (Some(Ok(ssid)), Ok(password), Ok(priority), Some(static_ip)) if !ssid.is_empty() => {
+                    Some(WifiNetwork { ssid, password, priority, static_ip })
+                }
+                _ => None,
+            };
+
+            //在副本上修改，保存成功后才替换
+            let mut wifi_info = WIFI_INFO.lock().await;
+            if let (Some(network), Some(current)) = (network, wifi_info.as_ref()) {
+                let mut updated = current.clone();
+                if updated.add_network(network) {
+                    match updated.write() {
                         Ok(_) => {
                             println!("保存成功");
-                            software_reset();
+                            wifi_info.replace(updated);
+                            success = true;
                         }
                         Err(e) => {
                             println!("保存失败：{:?}", e);
                         }
                     }
                 }
+            }
+        }
 
-                let r = socket
-                    .write_all(
-                        b"HTTP/1.0 200 OK\r\n\r\n\
-            <html>\
-                <body>\
-                   <form action='/restart' method='POST'>\
-                    <br/>\
-                    <br/>\
-                    <input type='submit' value='' />\
-                   </form>\
-                </body>\
-            </html>\r\n",
-                    )
-                    .await;
+        //配网模式下保存后重启，以 STA 模式连接
+        if success && *WIFI_MODEL.lock().await == Some(WifiModel::AP) {
+            result(success).then(software_reset)
+        } else {
+            result(success)
+        }
+    })
+}
 
-                if let Err(e) = r {
-                    println!("write error: {:?}", e);
+fn remove_wifi<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            let mut wifi_info = WIFI_INFO.lock().await;
+            if let (Some(ssid), Some(current)) = (form.get("ssid"), wifi_info.as_ref()) {
+                let mut updated = current.clone();
+                if updated.remove_network(ssid) {
+                    match updated.write() {
+                        Ok(_) => {
+                            println!("删除成功");
+                            wifi_info.replace(updated);
+                            success = true;
+                        }
+                        Err(e) => {
+                            println!("删除失败：{:?}", e);
+                        }
+                    }
                 }
             }
         }
-    }
+
+        result(success)
+    })
 }
-fn parse_form<'a>(
-    req: &httparse::Request,
-    buffer: &'a str,
-) -> Result<Vec<(&'a str, &'a str), 20>, &'static str> {
-    let (_, body) = buffer.split_once("\r\n\r\n").ok_or("Invalid request format")?;
-    let content_type = req
-        .headers
-        .iter()
-        .find(|h| h.name == "Content-Type")
-        .ok_or("No Content-Type header found")?;
-
-    let boundary = if content_type.value.starts_with(b"multipart/form-data") {
-        let boundary_str = from_utf8(content_type.value).map_err(|_| "Invalid Content-Type header")?;
-        boundary_str
-            .split(';')
-            .find_map(|part| part.trim().strip_prefix("boundary="))
-            .ok_or("No boundary found in Content-Type header")?
-    } else {
-        return Err("Content-Type is not multipart/form-data");
-    };
-    println!("boundary:{:?}",boundary);
-
-    let mut result: Vec<(&'a str, &'a str), 20> = Vec::new();
-    let form_fields: Vec<&str, 20> = body.split(boundary).collect();
-
-    for form_field in form_fields {
-        println!("form_field:{:?}",form_field);
-        let field = form_field.trim();
-        println!("form_field1:{:?}",field);
-
-        if field.contains("Content-Disposition: form-data;") {
-            println!("form_field2:{:?}",field);
-            if let Some(field) = field.strip_prefix("Content-Disposition: form-data;") {
-                println!("form_field3:{:?}",field);
-                if let Some((field_name, field_value)) = field.split_once("\r\n\r\n") {
-                    println!("form_field4:{:?}",field_name);
-                    println!("form_field5:{:?}",field_value);
-                    let field_name = field_name
-                        .split(';')
-                        .find_map(|part| part.trim().strip_prefix("name="))
-                        .ok_or("No name attribute found in form-data")?;
-                    let field_name = field_name.trim_matches('"');
-                    let field_value = field_value.trim_matches('-').trim();
-
-                    result.push((field_name, field_value)).map_err(|_| "Too many form fields")?;
+
+fn configure_pomodoro<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            let value = |name:&str| form.get(name).unwrap_or("").parse::<u32>().unwrap_or(0);
+            //页面上以分钟填写，换算成秒溢出时拒绝保存
+            let secs = |name:&str| value(name).checked_mul(60);
+            let pomodoro = match (secs("work"), secs("short_break"), secs("long_break")) {
+                (Some(work_secs), Some(short_break_secs), Some(long_break_secs)) => {
+                    Some(PomodoroStorage { work_secs, short_break_secs, long_break_secs, cycles: value("cycles") })
+                }
+                _ => None,
+            };
+
+            if let Some(pomodoro) = pomodoro.filter(|v| v.is_valid()) {
+                match pomodoro.write() {
+                    Ok(_) => {
+                        println!("保存成功");
+                        POMODORO_INFO.lock().await.replace(pomodoro);
+                        success = true;
+                    }
+                    Err(e) => {
+                        println!("保存失败：{:?}", e);
+                    }
                 }
             }
         }
 
-    }
+        result(success)
+    })
+}
 
-    if result.is_empty() {
-        Err("No valid form fields found")
-    } else {
-        Ok(result)
-    }
+//当前的类别，配置页面打开时填入表单
+fn work_items<'a>(_request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut content = alloc::string::String::from("{\"items\":[");
+        if let Some(work_items) = WORK_ITEM_INFO.lock().await.as_ref() {
+            for (index, item) in work_items.items.iter().enumerate() {
+                if index > 0 {
+                    content.push(',');
+                }
+                content.push_str(&json_string(&item.name));
+            }
+        }
+        content.push_str("]}");
+        Response::json(content)
+    })
+}
+
+//同名的类别保留原来的 id，已有的计时记录仍然对应原来的类别
+fn configure_work_items<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            let mut names:Vec<&str,{ WORK_ITEM_MAX + 1 }> = Vec::new();
+            let mut valid = true;
+            //每行或逗号分隔一个类别
+            for item in form.get("items").unwrap_or("").split(|c| c == '\n' || c == ',' || c == '，') {
+                let item = item.trim();
+                if item.is_empty() {
+                    continue;
+                }
+                if names.push(item).is_err() {
+                    valid = false;
+                }
+            }
+
+            let mut work_item_info = WORK_ITEM_INFO.lock().await;
+            let work_items = match work_item_info.as_ref() {
+                Some(current) if valid && !names.is_empty() => current.with_names(names.iter().copied()),
+                _ => None,
+            };
+            if let Some(work_items) = work_items {
+                match work_items.write() {
+                    Ok(_) => {
+                        println!("保存成功");
+                        work_item_info.replace(work_items);
+                        success = true;
+                    }
+                    Err(e) => {
+                        println!("保存失败：{:?}", e);
+                    }
+                }
+            }
+        }
+
+        result(success)
+    })
+}
+
+fn configure_intervals<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            let mut intervals = IntervalStorage { sequences: Vec::new() };
+            let mut valid = true;
+            //每行一个序列，任何一行格式错误都不保存
+            for line in form.get("sequences").unwrap_or("").lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match IntervalSequence::parse(line) {
+                    Some(sequence) => {
+                        if intervals.sequences.push(sequence).is_err() {
+                            valid = false;
+                        }
+                    }
+                    None => { valid = false; }
+                }
+            }
+
+            if valid && !intervals.sequences.is_empty() {
+                match intervals.write() {
+                    Ok(_) => {
+                        println!("保存成功");
+                        INTERVAL_INFO.lock().await.replace(intervals);
+                        success = true;
+                    }
+                    Err(e) => {
+                        println!("保存失败：{:?}", e);
+                    }
+                }
+            }
+        }
+
+        result(success)
+    })
+}
+
+fn configure_sync<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            //保存成功后才替换，失败时不影响正在使用的配置
+            let mut other_info = OTHER_INFO.lock().await;
+            let other = match (other_info.as_ref(), String::from_str(form.get("url").unwrap_or("")), String::from_str(form.get("token").unwrap_or(""))) {
+                (Some(current), Ok(sync_url), Ok(token)) => Some(OtherStorage { sync_url, token, ..current.clone() }),
+                _ => None,
+            };
+            if let Some(other) = other {
+                match other.write() {
+                    Ok(_) => {
+                        println!("保存成功");
+                        other_info.replace(other);
+                        success = true;
+                        TIMER_LOG_SYNC_SIGNAL.signal(());
+                    }
+                    Err(e) => {
+                        println!("保存失败：{:?}", e);
+                    }
+                }
+            }
+        }
+
+        result(success)
+    })
+}
+
+fn configure_device<'a>(request:&'a Request<'a>)->HandlerFuture<'a>{
+    Box::pin(async move {
+        let mut success = false;
+        if let Some(form) = parse_form(request) {
+            let name = form.get("device_name").map(|v| v.trim()).unwrap_or("");
+            //为空时恢复默认名称
+            if name.is_empty() || is_valid_name(name) {
+                //保存成功后才替换，mDNS 不会发布没有保存的名称
+                let mut other_info = OTHER_INFO.lock().await;
+                let other = match (other_info.as_ref(), String::from_str(name)) {
+                    (Some(current), Ok(device_name)) => Some(OtherStorage { device_name, ..current.clone() }),
+                    _ => None,
+                };
+                if let Some(other) = other {
+                    match other.write() {
+                        Ok(_) => {
+                            println!("保存成功");
+                            other_info.replace(other);
+                            success = true;
+                            MDNS_UPDATE_SIGNAL.signal(());
+                        }
+                        Err(e) => {
+                            println!("保存失败：{:?}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        result(success)
+    })
 }
 
@@ -0,0 +1,99 @@
+use alloc::format;
+use alloc::string::String;
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::Drawable;
+use embedded_graphics::prelude::{PixelColor, Point, Primitive, Size};
+use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
+use heapless::Vec;
+use u8g2_fonts::FontRenderer;
+use u8g2_fonts::fonts;
+use u8g2_fonts::types::{FontColor, HorizontalAlignment, VerticalPosition};
+
+const ROW_HEIGHT:u32 = 14;
+const LABEL_WIDTH:u32 = 48;
+const VALUE_WIDTH:u32 = 56;
+
+//水平条形图，每行一个 标签、条、数值，数值单位为秒，显示为时分
+pub struct BarChart<'a,C>{
+    position:Point,
+    size:Size,
+    front_color:C,
+    back_color:C,
+    items:Vec<(&'a str,u64),12>,
+}
+
+impl <'a,C> BarChart<'a,C>{
+    pub fn new(position: Point,size: Size,front_color:C,back_color:C,items:Vec<(&'a str,u64),12>)->Self{
+        Self{
+            position,
+            size,
+            front_color,
+            back_color,
+            items,
+        }
+    }
+
+    //能显示的行数
+    pub fn max_rows(&self)->usize{
+        (self.size.height / ROW_HEIGHT) as usize
+    }
+}
+
+pub fn format_secs(secs:u64)->String{
+    let hour = secs / 3600;
+    let minute = secs / 60 % 60;
+    if hour > 0 {
+        format!("{}时{}分",hour,minute)
+    }else{
+        format!("{}分",minute)
+    }
+}
+
+impl <'a,C> Drawable for BarChart<'a,C> where C:PixelColor{
+    type Color = C;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+        where D: DrawTarget<Color=Self::Color> {
+        let max_value = self.items.iter().map(|v| v.1).max().unwrap_or(0).max(1);
+        let bar_max_width = self.size.width.saturating_sub(LABEL_WIDTH + VALUE_WIDTH);
+
+        let font: FontRenderer = FontRenderer::new::<fonts::u8g2_font_wqy12_t_gb2312>();
+        let bar_style = PrimitiveStyleBuilder::new()
+            .fill_color(self.front_color)
+            .build();
+
+        for (index,(label,value)) in self.items.iter().take(self.max_rows()).enumerate() {
+            let y = self.position.y + (index as u32 * ROW_HEIGHT) as i32;
+            let center_y = y + ROW_HEIGHT as i32 / 2;
+
+            let _ = font.render_aligned(
+                *label,
+                Point::new(self.position.x, center_y),
+                VerticalPosition::Center,
+                HorizontalAlignment::Left,
+                FontColor::Transparent(self.front_color),
+                target,
+            );
+
+            let bar_width = (*value * bar_max_width as u64 / max_value) as u32;
+            if bar_width > 0 {
+                Rectangle::new(Point::new(self.position.x + LABEL_WIDTH as i32, y + 2)
+                               , Size::new(bar_width, ROW_HEIGHT - 4))
+                    .into_styled(bar_style)
+                    .draw(target)?;
+            }
+
+            let _ = font.render_aligned(
+                format_secs(*value).as_str(),
+                Point::new(self.position.x + (LABEL_WIDTH + bar_width + 2) as i32, center_y),
+                VerticalPosition::Center,
+                HorizontalAlignment::Left,
+                FontColor::Transparent(self.front_color),
+                target,
+            );
+        }
+
+        Ok(())
+    }
+}
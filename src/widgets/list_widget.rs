@@ -14,6 +14,17 @@ use crate::widgets::scroll_bar::{ScrollBar, ScrollBarDirection};
 
 const ITEM_HEIGHT:u32 = 20;
 const SCROLL_WIDTH:u32 = 10;
+
+//列表项文字不能超过 20 字节，按字符截断
+pub fn list_item_text(text:&str)->String<20>{
+    let mut item = String::new();
+    for c in text.chars() {
+        if item.push(c).is_err() {
+            break;
+        }
+    }
+    item
+}
 //每个widget 包含状态与绘制，widget 没有业务逻辑只有通过对应事件回调触发
 pub struct ListWidget<C>{
     position:Point,
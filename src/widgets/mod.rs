@@ -3,4 +3,5 @@ mod scroll_bar;
 pub mod calendar;
 pub mod qrcode_widget;
 pub mod clock_widget;
-pub mod battery_widget;
\ No newline at end of file
+pub mod battery_widget;
+pub mod bar_chart;
\ No newline at end of file
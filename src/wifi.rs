@@ -1,34 +1,42 @@
 use alloc::string::ToString;
 use core::net::Ipv4Addr;
+use core::cell::RefCell;
+use core::future::poll_fn;
 use core::ops::{Deref, DerefMut};
+use core::task::Poll;
 use core::str::{from_utf8, FromStr};
-use dhcparse::dhcpv4::{Addr, DhcpOption, Encode, Encoder, Message};
-use dhcparse::v4_options;
+use dhcparse::dhcpv4::Message;
 use embassy_executor::Spawner;
-use embassy_futures::select::{Either, select};
-use embassy_net::{Config, IpAddress, IpEndpoint, IpListenEndpoint, Ipv4Address, Ipv4Cidr, Stack, StackResources, StaticConfigV4};
+use embassy_futures::select::{Either3, select, select3};
+use embassy_net::{Config, ConfigV4, IpAddress, IpEndpoint, IpListenEndpoint, Ipv4Address, Ipv4Cidr, Stack, StackResources, StaticConfigV4};
 use embassy_net::tcp::{AcceptError, TcpSocket};
 use embassy_net::udp::UdpSocket;
+use embassy_sync::blocking_mutex::Mutex as BlockingMutex;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::mutex::Mutex;
 use embassy_sync::signal::Signal;
-use embassy_time::{Duration, Instant, Timer};
+use embassy_sync::waitqueue::MultiWakerRegistration;
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use esp_println::{print, println};
 use esp_storage::FlashStorageError;
 use esp_wifi::{EspWifiInitFor, initialize};
 use esp_wifi::wifi::{AccessPointConfiguration, AuthMethod, ClientConfiguration, Configuration, WifiApDevice, WifiController, WifiDevice, WifiError, WifiEvent, WifiStaDevice, WifiState};
 use esp_wifi::wifi::ipv4::{ RouterConfiguration, SocketAddrV4};
 use hal::clock::Clocks;
+use hal::macros::ram;
 use hal::peripherals::{RADIO_CLK, SYSTIMER, TIMG0, WIFI};
 use hal::reset::software_reset;
 use hal::rng::Rng;
 use hal::system::SystemClockControl;
 use hal::timer::PeriodicTimer;
-use heapless::{String, Vec};
+use heapless::{Deque, String, Vec};
 use httparse::Header;
 use static_cell::{ StaticCell};
+use crate::dhcp::{DHCP_PACKET_SIZE, DhcpReply, DhcpServer};
+use crate::mdns::{DEFAULT_DEVICE_NAME, DEVICE_NAME_MAX, Mdns, MDNS_ADDR, MDNS_PACKET_SIZE, MDNS_PORT, MdnsReply};
 use crate::make_static;
-use crate::storage::{NvsStorage, WIFI_INFO};
+use crate::storage::{NvsStorage, OTHER_INFO, WIFI_INFO, WifiNetwork};
+use crate::web_service::WEB_SERVICE_RUNNING;
 
 #[derive(Eq, PartialEq,Copy, Clone,Debug)]
 pub enum WifiModel{
@@ -44,25 +52,112 @@ pub enum WifiNetState {
 }
 #[derive(Debug)]
 pub enum WifiNetError {
-    WaitConnecting,
-    TimeOut,
-    Infallible,
-    Using,
+    RadioOff,//wifi 没有初始化，或关闭后没能重新打开
+    TimeOut,//排队或等待连接超时
+    Busy,//排队的请求太多
 }
 
 
 
 
-const SSID: &str = env!("SSID");
-const PASSWORD: &str = env!("PASSWORD");
 
-const HOW_LONG_SECS_CLOSE:u64 = 30;//20秒未使用wifi 断开
+const HOW_LONG_SECS_CLOSE:u64 = 30;//没有任务使用 wifi 30 秒后断开
+const CONNECT_FAIL_MAX:u32 = 10;//连续连接失败次数，超过后进入配网模式
+//扫描时能看到信号足够的网络却连续连接失败，很可能是密码错误，较少次数后就进入配网模式
+//esp-wifi 不提供断开的原因码，只能这样推测，路由器拒绝连接(例如 MAC 过滤、连接数已满)也会被当作密码错误
+const REJECTED_FAIL_MAX:u32 = 3;
+const USABLE_SIGNAL_STRENGTH:i8 = -75;
+const AP_RETRY_SECS:u64 = 60;//配网模式下重新尝试已保存网络的间隔
+const AP_FALLBACK_MAGIC:u32 = 0x4150_4642;
+
+//连接失败后重启进入配网模式的标记，只保存在 rtc 中，断电后回到 STA 模式
+//esp-wifi 不提供断开的原因码，无法区分密码错误与路由器暂时不可用，所以不写入 flash
+#[ram(rtc_fast, persistent)]
+static mut AP_FALLBACK:u32 = 0;
 
 pub static mut IP_ADDRESS:String<20> = String::new();
+//当前连接的网络
+pub static CONNECTED_SSID:Mutex<CriticalSectionRawMutex,Option<String<32>>> = Mutex::new(None);
+
+pub const SCAN_MAX:usize = 20;
+#[derive(Debug,Clone)]
+pub struct ScanNetwork{
+    pub ssid:String<32>,
+    pub bssid:[u8;6],
+    pub channel:u8,
+    pub signal_strength:i8,
+    pub auth_method:Option<AuthMethod>,
+}
+
+impl ScanNetwork {
+    pub fn secured(&self)->bool{
+        !matches!(self.auth_method, Some(AuthMethod::None))
+    }
+}
+pub const DISCONNECT_HISTORY_MAX:usize = 5;
+//断开的原因，esp-wifi 的 StaDisconnected 事件不带原因码，只记录本地能判断的情况
+#[derive(Debug,Clone,Copy,Eq,PartialEq)]
+pub enum DisconnectReason{
+    LinkLost,//收到 StaDisconnected 事件
+    LostWhileScanning,//扫描期间断开
+    RadioStopped,//空闲时被 do_stop 关闭，或休眠前关闭
+    ConnectFailed,//连接失败
+}
+
+impl DisconnectReason {
+    pub fn text(&self)->&'static str{
+        match self {
+            DisconnectReason::LinkLost => "连接断开",
+            DisconnectReason::LostWhileScanning => "扫描时断开",
+            DisconnectReason::RadioStopped => "关闭wifi",
+            DisconnectReason::ConnectFailed => "连接失败",
+        }
+    }
+}
+
+//连接状态，给网络状态页面显示
+pub struct LinkStatus{
+    pub ap:Option<ScanNetwork>,//连接的网络在最近一次扫描中的信息
+    pub connected_at:Option<u64>,//连接成功的时间，秒
+    pub stopped_at:Option<u64>,//最近一次关闭 wifi 的时间，秒
+    pub disconnects:Deque<(u64,DisconnectReason),DISCONNECT_HISTORY_MAX>,//最早的在前
+}
+
+pub static LINK_STATUS:Mutex<CriticalSectionRawMutex,LinkStatus> = Mutex::new(LinkStatus{
+    ap: None,
+    connected_at: None,
+    stopped_at: None,
+    disconnects: Deque::new(),
+});
+
+async fn record_connected(ssid:&str){
+    let ap = SCAN_RESULTS.lock().await.iter().find(|v| v.ssid == ssid).cloned();
+    let mut status = LINK_STATUS.lock().await;
+    status.ap = ap;
+    status.connected_at = Some(Instant::now().as_secs());
+}
+
+async fn record_disconnect(reason:DisconnectReason){
+    let now = Instant::now().as_secs();
+    let mut status = LINK_STATUS.lock().await;
+    status.connected_at = None;
+    if reason == DisconnectReason::RadioStopped {
+        status.stopped_at = Some(now);
+    }
+    if status.disconnects.is_full() {
+        status.disconnects.pop_front();
+    }
+    let _ = status.disconnects.push_back((now,reason));
+}
+
+static SCAN_RESULTS:Mutex<CriticalSectionRawMutex,Vec<ScanNetwork,SCAN_MAX>> = Mutex::new(Vec::new());
+static SCAN_REQUEST_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+static SCAN_DONE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static STOP_WIFI_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static RECONNECT_WIFI_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static REINIT_WIFI_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
-pub static LAST_USE_TIME_SECS:Mutex<CriticalSectionRawMutex,Option<u64>>  =  Mutex::new(None);
+//设备名修改后重新发布 mDNS 记录
+pub static MDNS_UPDATE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static WIFI_STATE:Mutex<CriticalSectionRawMutex,Option<WifiNetState>>  =  Mutex::new(None);
 pub static mut STACK_MUT: Option<&'static Stack<WifiDevice<'static, WifiStaDevice>>>  =  None;
 pub static mut AP_STACK_MUT: Option<&'static Stack<WifiDevice<'static, WifiApDevice>>>  =  None;
@@ -123,11 +218,9 @@ pub async fn connect_wifi(spawner: &Spawner,
         unsafe {
             AP_STACK_MUT = Some(ap_stack);
         }
-        let client_config = ClientConfiguration {
-            ssid: SSID.try_into().unwrap(),
-            password: PASSWORD.try_into().unwrap(),
-            auth_method: AuthMethod::None,
-            ..Default::default()
+        let client_config = match WIFI_INFO.lock().await.as_ref().and_then(|v| v.preferred()) {
+            Some(network) => client_configuration(network),
+            None => ClientConfiguration::default(),
         };
         let ap_config =  AccessPointConfiguration {
             ssid: "esp-wifi".try_into().unwrap(),
@@ -150,15 +243,16 @@ pub async fn connect_wifi(spawner: &Spawner,
         Stack::new(
         wifi_interface,
         config,
-        make_static!(StackResources::<3>,StackResources::<3>::new()),
+        make_static!(StackResources::<5>,StackResources::<5>::new()),
         seed
     ));
 
 
-    LAST_USE_TIME_SECS.lock().await.replace(Instant::now().as_secs());
+    release_now();
 
-    spawner.spawn(connection_wifi(controller)).ok();
+    spawner.spawn(connection_wifi(controller, stack)).ok();
     spawner.spawn(net_task(stack)).ok();
+    spawner.spawn(mdns_service(stack)).ok();
     spawner.spawn(do_stop()).ok();
     loop {
         println!("Waiting is_link_up...");
@@ -185,6 +279,109 @@ pub async fn connect_wifi(spawner: &Spawner,
     Ok(stack)
 }
 
+fn client_configuration(network:&WifiNetwork)->ClientConfiguration{
+    ClientConfiguration {
+        ssid: network.ssid.clone(),
+        password: network.password.clone(),
+        auth_method: if network.password.is_empty() { AuthMethod::None } else { AuthMethod::WPA2Personal },
+        ..Default::default()
+    }
+}
+
+//网络设置了静态 IP 时使用，否则使用 DHCP
+fn ip_config(network:&WifiNetwork)->ConfigV4{
+    match network.static_ip {
+        Some(static_ip) => {
+            let mut dns_servers = Vec::new();
+            for dns in static_ip.dns_servers.iter().filter(|v| **v != [0;4]) {
+                let _ = dns_servers.push(Ipv4Address::from_bytes(dns));
+            }
+            ConfigV4::Static(StaticConfigV4 {
+                address: Ipv4Cidr::new(Ipv4Address::from_bytes(&static_ip.address), static_ip.prefix_len),
+                gateway: Some(Ipv4Address::from_bytes(&static_ip.gateway)),
+                dns_servers,
+            })
+        }
+        None => ConfigV4::Dhcp(Default::default()),
+    }
+}
+
+//扫描到的网络，同一个 SSID 有多个接入点时只保留信号最强的
+async fn scan(controller:&mut WifiController<'static>){
+    match controller.scan_n::<SCAN_MAX>().await {
+        Ok((list,_)) => {
+            let mut results = SCAN_RESULTS.lock().await;
+            results.clear();
+            for ap in list.iter() {
+                if ap.ssid.is_empty() {
+                    continue;
+                }
+                let network = ScanNetwork{
+                    ssid: ap.ssid.clone(),
+                    bssid: ap.bssid,
+                    channel: ap.channel,
+                    signal_strength: ap.signal_strength,
+                    auth_method: ap.auth_method,
+                };
+                match results.iter_mut().find(|v| v.ssid == ap.ssid) {
+                    Some(v) => {
+                        if ap.signal_strength > v.signal_strength {
+                            *v = network;
+                        }
+                    }
+                    None => {
+                        let _ = results.push(network);
+                    }
+                }
+            }
+            println!("scan found {} networks",results.len());
+            //刷新当前连接的网络的信号强度
+            if let Some(ssid) = CONNECTED_SSID.lock().await.as_ref() {
+                if let Some(ap) = results.iter().find(|v| v.ssid == *ssid) {
+                    LINK_STATUS.lock().await.ap = Some(ap.clone());
+                }
+            }
+        }
+        Err(e) => {
+            println!("scan error: {:?}",e);
+        }
+    }
+    SCAN_DONE_SIGNAL.signal(());
+}
+
+//请求重新扫描，STA 模式下 wifi 关闭时不会扫描，超时后返回上次扫描的结果
+pub async fn scan_networks()->Vec<ScanNetwork,SCAN_MAX>{
+    SCAN_DONE_SIGNAL.reset();
+    SCAN_REQUEST_SIGNAL.signal(());
+    let _ = with_timeout(Duration::from_secs(10), SCAN_DONE_SIGNAL.wait()).await;
+    SCAN_RESULTS.lock().await.clone()
+}
+
+//在扫描到的网络中选择已保存的，优先级高的优先，优先级相同时选信号强的
+//同时返回扫描到的信号强度，没有扫描到时为 None
+async fn choose_network()->Option<(WifiNetwork,Option<i8>)>{
+    let results = SCAN_RESULTS.lock().await;
+    let wifi_info = WIFI_INFO.lock().await;
+    let wifi_info = wifi_info.as_ref()?;
+    let mut best:Option<(&WifiNetwork,i8)> = None;
+    for ap in results.iter() {
+        let Some(network) = wifi_info.networks.iter().find(|v| v.ssid == ap.ssid) else { continue; };
+        let better = match best {
+            Some((current,signal_strength)) => {
+                (network.priority,ap.signal_strength) > (current.priority,signal_strength)
+            }
+            None => true,
+        };
+        if better {
+            best = Some((network,ap.signal_strength));
+        }
+    }
+    match best {
+        Some((network,signal_strength)) => Some((network.clone(),Some(signal_strength))),
+        None => wifi_info.preferred().map(|v| (v.clone(),None)),
+    }
+}
+
 #[embassy_executor::task]
 async fn ap_task(stack: &'static Stack<WifiDevice<'static, WifiApDevice>>) {
     stack.run().await
@@ -195,33 +392,141 @@ async fn net_task(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>) {
     stack.run().await
 }
 
+//等待地址失效、变化或设备名修改，需要重新加入组播组并发布
+async fn wait_mdns_changed(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>, ip:[u8;4]){
+    let address_changed = async {
+        loop {
+            Timer::after(Duration::from_millis(1000)).await;
+            if stack.config_v4().map(|v| v.address.address().0) != Some(ip) {
+                break;
+            }
+        }
+    };
+    select(address_changed, MDNS_UPDATE_SIGNAL.wait()).await;
+}
+
+//mDNS 服务，获取到地址后加入组播组并发布记录，局域网内可以用 <设备名>.local 访问
+//wifi 被 do_stop 关闭期间无法访问，网页配置服务只在设置页面打开期间发布
 #[embassy_executor::task]
-async fn connection_wifi(mut controller: WifiController<'static>) {
+async fn mdns_service(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>){
+    const PACKET_META_SIZE: usize = 4;
+    let group = Ipv4Address::from_bytes(&MDNS_ADDR);
+
+    loop {
+        stack.wait_config_up().await;
+        let Some(config) = stack.config_v4() else { continue; };
+        let ip = config.address.address().0;
+        //先清除信号再读取状态，读取之后的修改会触发下一次发布
+        MDNS_UPDATE_SIGNAL.reset();
+        let name:String<DEVICE_NAME_MAX> = match OTHER_INFO.lock().await.as_ref() {
+            Some(other) => String::from_str(other.device_name()).unwrap_or_default(),
+            None => String::from_str(DEFAULT_DEVICE_NAME).unwrap(),
+        };
+        let http = *WEB_SERVICE_RUNNING.lock().await;
+
+        if let Err(e) = stack.join_multicast_group(group).await {
+            println!("mdns join group error: {:?}", e);
+            Timer::after(Duration::from_millis(5000)).await;
+            continue;
+        }
+
+        let mut rx_meta = [embassy_net::udp::PacketMetadata::EMPTY; PACKET_META_SIZE];
+        let mut rx_buffer = [0u8; MDNS_PACKET_SIZE];
+        let mut tx_meta = [embassy_net::udp::PacketMetadata::EMPTY; PACKET_META_SIZE];
+        let mut tx_buffer = [0u8; MDNS_PACKET_SIZE];
+        let mut udp_socket = UdpSocket::new(stack, &mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
+        if let Err(e) = udp_socket.bind(MDNS_PORT) {
+            println!("mdns bind error: {:?}", e);
+        }
+        println!("mdns: {}.local -> {}", name, config.address.address());
+
+        let mdns = Mdns::new(&name, ip, http);
+        let mut response = Vec::new();
+        if mdns.announce(&mut response).is_some() {
+            if let Err(e) = udp_socket.send_to(&response, (group, MDNS_PORT)).await {
+                println!("mdns send error: {:?}", e);
+            }
+        }
+
+        let serve = async {
+            let mut buf = [0u8; MDNS_PACKET_SIZE];
+            loop {
+                let Ok((n, src)) = udp_socket.recv_from(&mut buf).await else { continue; };
+                match mdns.handle(&buf[..n], src.port != MDNS_PORT, &mut response) {
+                    Some(MdnsReply::Multicast) => {
+                        if let Err(e) = udp_socket.send_to(&response, (group, MDNS_PORT)).await {
+                            println!("mdns send error: {:?}", e);
+                        }
+                    }
+                    Some(MdnsReply::Unicast) => {
+                        if let Err(e) = udp_socket.send_to(&response, src).await {
+                            println!("mdns send error: {:?}", e);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        };
+        select(serve, wait_mdns_changed(stack, ip)).await;
+        //撤销已发布的服务，仍在运行时下一轮会重新发布
+        if mdns.goodbye(&mut response).is_some() {
+            if let Err(e) = udp_socket.send_to(&response, (group, MDNS_PORT)).await {
+                println!("mdns send error: {:?}", e);
+            }
+        }
+        let _ = stack.leave_multicast_group(group).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn connection_wifi(mut controller: WifiController<'static>, stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>) {
     println!("start connection task");
     println!("Device capabilities: {:?}", controller.get_capabilities());
+    let mut fail_count = 0;
+    //信号足够却连接失败的网络与连续失败次数
+    let mut rejected:Option<(String<32>,u32)> = None;
     loop {
         match esp_wifi::wifi::get_wifi_state() {
             WifiState::StaConnected => {
                 // wait until we're no longer connected
                 WIFI_STATE.lock().await.replace(WifiNetState::WifiConnected);
-                let disconnect =  controller.wait_for_event(WifiEvent::StaDisconnected);
-                let closeconnect = STOP_WIFI_SIGNAL.wait();
-
-                match select(disconnect,closeconnect).await {
-                    Either::First(_) => {
-                        WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);
-                        Timer::after(Duration::from_millis(1000)).await
-                    }
-                    Either::Second(_) => {
-                        STOP_WIFI_SIGNAL.reset();
-                        controller.stop().await.expect("wifi stop error");
-                        println!("wifi close...");
-                        WIFI_STATE.lock().await.replace(WifiNetState::WifiStopped);
-                        RECONNECT_WIFI_SIGNAL.wait().await;
-                        RECONNECT_WIFI_SIGNAL.reset();
-                        println!("restart connect...");
-                        WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);
-
+                loop {
+                    let disconnect =  controller.wait_for_event(WifiEvent::StaDisconnected);
+                    let closeconnect = STOP_WIFI_SIGNAL.wait();
+                    let scan_request = SCAN_REQUEST_SIGNAL.wait();
+
+                    match select3(disconnect,closeconnect,scan_request).await {
+                        Either3::First(_) => {
+                            WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);
+                            CONNECTED_SSID.lock().await.take();
+                            record_disconnect(DisconnectReason::LinkLost).await;
+                            Timer::after(Duration::from_millis(1000)).await;
+                            break;
+                        }
+                        Either3::Second(_) => {
+                            STOP_WIFI_SIGNAL.reset();
+                            controller.stop().await.expect("wifi stop error");
+                            println!("wifi close...");
+                            WIFI_STATE.lock().await.replace(WifiNetState::WifiStopped);
+                            CONNECTED_SSID.lock().await.take();
+                            record_disconnect(DisconnectReason::RadioStopped).await;
+                            RECONNECT_WIFI_SIGNAL.wait().await;
+                            RECONNECT_WIFI_SIGNAL.reset();
+                            println!("restart connect...");
+                            WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);
+                            break;
+                        }
+                        Either3::Third(_) => {
+                            SCAN_REQUEST_SIGNAL.reset();
+                            scan(&mut controller).await;
+                            //扫描期间断开时不会再收到断开事件
+                            if !matches!(controller.is_connected(), Ok(true)) {
+                                WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);
+                                CONNECTED_SSID.lock().await.take();
+                                record_disconnect(DisconnectReason::LostWhileScanning).await;
+                                break;
+                            }
+                        }
                     }
                 }
 
@@ -229,110 +534,235 @@ async fn connection_wifi(mut controller: WifiController<'static>) {
             _ => { WIFI_STATE.lock().await.replace(WifiNetState::WifiDisconnected);}
         }
         if !matches!(controller.is_started(), Ok(true)) {
-            loop {
-                if let Some(ref wifi_info) = *WIFI_INFO.lock().await {
-                    let client_config = Configuration::Client(ClientConfiguration {
-                        ssid:SSID.try_into().unwrap(),//wifi_info.wifi_ssid.clone(), //SSID.try_into().unwrap(),
-                        password:PASSWORD.try_into().unwrap(),//wifi_info.wifi_password.clone(), //PASSWORD.try_into().unwrap(),
-                        ..Default::default()
-                    });
-                    match controller.set_configuration(&client_config) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            println!("配置失败：{:?}",e);
-                        }
-                    }
-                    println!("Starting wifi");
-                    controller.start().await.unwrap();
-                    println!("Wifi started!");
-                    break;
-                } else {
-                    Timer::after(Duration::from_millis(50)).await;
+            let client_config = Configuration::Client(ClientConfiguration::default());
+            match controller.set_configuration(&client_config) {
+                Ok(_) => {}
+                Err(e) => {
+                    println!("配置失败：{:?}",e);
                 }
             }
+            println!("Starting wifi");
+            controller.start().await.unwrap();
+            println!("Wifi started!");
+        }
+
+        //每次重连前扫描，连接已保存的网络中最合适的一个
+        SCAN_REQUEST_SIGNAL.reset();
+        scan(&mut controller).await;
+        let Some((network,signal_strength)) = choose_network().await else {
+            println!("no saved wifi");
+            Timer::after(Duration::from_millis(5000)).await;
+            continue;
+        };
+        match controller.set_configuration(&Configuration::Client(client_configuration(&network))) {
+            Ok(_) => {}
+            Err(e) => {
+                println!("配置失败：{:?}",e);
+            }
         }
-        println!("About to connect...");
+        stack.set_config_v4(ip_config(&network));
+        println!("About to connect {}...",network.ssid);
 
         WIFI_STATE.lock().await.replace(WifiNetState::WifiConnecting);
         match controller.connect().await {
             Ok(_) =>{
-                println!("Wifi connected!");
+                println!("Wifi connected to {}!",network.ssid);
+                fail_count = 0;
+                rejected = None;
+                CONNECTED_SSID.lock().await.replace(network.ssid.clone());
+                record_connected(&network.ssid).await;
                 WIFI_STATE.lock().await.replace(WifiNetState::WifiConnected);
 
             },
             Err(e) => {
                 println!("Failed to connect to wifi: {e:?}");
+                record_disconnect(DisconnectReason::ConnectFailed).await;
+                fail_count += 1;
+                if signal_strength.is_some_and(|v| v >= USABLE_SIGNAL_STRENGTH) {
+                    let count = match rejected.take() {
+                        Some((ssid,count)) if ssid == network.ssid => count + 1,
+                        _ => 1,
+                    };
+                    rejected = Some((network.ssid.clone(),count));
+                    if count >= REJECTED_FAIL_MAX {
+                        println!("wifi {} visible but rejected {} times, password may be wrong",network.ssid,count);
+                        fallback_to_ap().await;
+                    }
+                }
+                if fail_count >= CONNECT_FAIL_MAX {
+                    fallback_to_ap().await;
+                }
                 Timer::after(Duration::from_millis(5000)).await
             }
         }
     }
 }
 
-pub async fn refresh_last_time(){
-    LAST_USE_TIME_SECS.lock().await.replace(Instant::now().as_secs());
+//多次连接失败，重启进入配网模式，已保存的网络保留
+//配网模式下会定时重试已保存的网络，连接成功或保存新的网络后重启回到 STA 模式
+async fn fallback_to_ap(){
+    println!("wifi connect failed too many times, restart to ap mode");
+    unsafe {
+        AP_FALLBACK = AP_FALLBACK_MAGIC;
+    }
+    software_reset();
 }
 
+//启动时检查是否因为连接失败进入配网模式，只生效一次
+pub fn take_ap_fallback()->bool{
+    unsafe {
+        let fallback = AP_FALLBACK == AP_FALLBACK_MAGIC;
+        AP_FALLBACK = 0;
+        fallback
+    }
+}
 
-//每次请求都要获取stack 并修改 WIFI_LOCK 为true,用完后 改回，同时多个任务容易崩
+//同时使用网络的任务数量，StackResources 中 DHCP、DNS、mDNS 各占一个，剩下的给每个任务一个 TCP socket
+const LEASE_MAX:usize = 2;
+const LEASE_QUEUE_MAX:usize = 8;
 const TIME_OUT_SECS: u64 = 10;
-static WIFI_LOCK:Mutex<CriticalSectionRawMutex,bool> = Mutex::new(false);
-pub async fn use_wifi() ->Result<&'static Stack<WifiDevice<'static, WifiStaDevice>>, WifiNetError>{
-    let secs = Instant::now().as_secs();
+
+//网络使用权的排队状态，按请求的顺序分配
+struct LeaseQueue{
+    next_id:u32,
+    waiting:Vec<u32,LEASE_QUEUE_MAX>,
+    active:usize,
+    released_at:u64,//最后一个使用者归还的时间，秒
+    wakers:MultiWakerRegistration<LEASE_QUEUE_MAX>,
+}
+
+static LEASE_QUEUE:BlockingMutex<CriticalSectionRawMutex,RefCell<LeaseQueue>> = BlockingMutex::new(RefCell::new(LeaseQueue{
+    next_id: 0,
+    waiting: Vec::new(),
+    active: 0,
+    released_at: 0,
+    wakers: MultiWakerRegistration::new(),
+}));
+
+fn release_now(){
+    LEASE_QUEUE.lock(|queue| queue.borrow_mut().released_at = Instant::now().as_secs());
+}
+
+//排队中的请求，等待被取消（future 被丢弃）时从队列中移除
+struct LeaseTicket{
+    id:u32,
+}
+
+impl LeaseTicket {
+    fn new()->Result<Self,WifiNetError>{
+        LEASE_QUEUE.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            let id = queue.next_id;
+            queue.waiting.push(id).map_err(|_| WifiNetError::Busy)?;
+            queue.next_id = id.wrapping_add(1);
+            Ok(Self{ id })
+        })
+    }
+
+    //排到队首且有空位时得到使用权
+    async fn wait(self)->LeaseSlot{
+        poll_fn(|cx| LEASE_QUEUE.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            if queue.waiting.first() == Some(&self.id) && queue.active < LEASE_MAX {
+                queue.waiting.remove(0);
+                queue.active += 1;
+                queue.wakers.wake();
+                Poll::Ready(())
+            } else {
+                queue.wakers.register(cx.waker());
+                Poll::Pending
+            }
+        })).await;
+        core::mem::forget(self);
+        LeaseSlot
+    }
+}
+
+impl Drop for LeaseTicket {
+    fn drop(&mut self) {
+        LEASE_QUEUE.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            if let Some(index) = queue.waiting.iter().position(|v| *v == self.id) {
+                queue.waiting.remove(index);
+            }
+            queue.wakers.wake();
+        });
+    }
+}
+
+//占用的一个位置，drop 时归还
+struct LeaseSlot;
+
+impl Drop for LeaseSlot {
+    fn drop(&mut self) {
+        LEASE_QUEUE.lock(|queue| {
+            let mut queue = queue.borrow_mut();
+            queue.active -= 1;
+            if queue.active == 0 {
+                queue.released_at = Instant::now().as_secs();
+            }
+            queue.wakers.wake();
+        });
+    }
+}
+
+///网络使用权，由 use_wifi 获取，离开作用域时自动归还
+///有使用者或排队的请求时 do_stop 不会关闭 wifi
+pub struct NetLease{
+    _slot:LeaseSlot,
+    stack:&'static Stack<WifiDevice<'static, WifiStaDevice>>,
+}
+
+impl NetLease {
+    pub fn stack(&self)->&'static Stack<WifiDevice<'static, WifiStaDevice>>{
+        self.stack
+    }
+}
+
+impl Deref for NetLease {
+    type Target = Stack<WifiDevice<'static, WifiStaDevice>>;
+
+    fn deref(&self) -> &Self::Target {
+        self.stack
+    }
+}
+
+//获取网络，wifi 关闭时重新打开，按请求顺序排队，连接并获取到地址后返回
+pub async fn use_wifi() ->Result<NetLease, WifiNetError>{
+    let begin = Instant::now();
+    let timeout = Duration::from_secs(TIME_OUT_SECS);
+    //先排队，避免检查状态后 wifi 被 do_stop 关闭
+    let ticket = LeaseTicket::new()?;
+
     if *WIFI_STATE.lock().await == None {
         REINIT_WIFI_SIGNAL.signal(());
         loop {
             if *WIFI_STATE.lock().await != None { break; }
-            if Instant::now().as_secs() - secs > 3 {
-                return Err(WifiNetError::WaitConnecting);
+            if begin.elapsed().as_secs() > 3 {
+                return Err(WifiNetError::RadioOff);
             }
             Timer::after_millis(10).await;
         }
     }
-    if WIFI_STATE.lock().await.unwrap() != WifiNetState::WifiConnected {
-        println!("need wait");
-    }
     if WIFI_STATE.lock().await.unwrap() == WifiNetState::WifiStopped {
         println!("send reconnect signal...");
         RECONNECT_WIFI_SIGNAL.signal(());
     }
 
-
-
-    loop {
-        refresh_last_time().await;
-        println!("use_wifi Waiting is_link_up...");
-        unsafe {
-            if let Some(v) = STACK_MUT {
-                if v.is_link_up() {
-
-                    loop {
-                        if !*WIFI_LOCK.lock().await  {
-                            break;
-                        }
-                        if Instant::now().as_secs() - secs > TIME_OUT_SECS  {
-                            return Err(WifiNetError::TimeOut);
-                        }
-                        Timer::after(Duration::from_millis(50)).await;
-                    }
-                    *WIFI_LOCK.lock().await = true;
-                    v.wait_config_up().await;
-                    return Ok(v);
-
-                }else if Instant::now().as_secs() - secs > TIME_OUT_SECS {
-                    return Err(WifiNetError::TimeOut);
+    let slot = with_timeout(timeout, ticket.wait()).await.map_err(|_| WifiNetError::TimeOut)?;
+    let remaining = timeout.checked_sub(begin.elapsed()).unwrap_or(Duration::from_ticks(0));
+    let stack = with_timeout(remaining, async {
+        loop {
+            if let Some(stack) = unsafe { STACK_MUT } {
+                if stack.is_link_up() {
+                    stack.wait_config_up().await;
+                    break stack;
                 }
-            }else{
-                return Err(WifiNetError::Infallible);
             }
+            Timer::after(Duration::from_millis(100)).await;
         }
-        Timer::after(Duration::from_millis(500)).await;
-    }
-}
-
-pub async fn finish_wifi(){
-
-    *WIFI_LOCK.lock().await   = false;
-    println!("finish_wifi");
+    }).await.map_err(|_| WifiNetError::TimeOut)?;
+    Ok(NetLease{ _slot: slot, stack })
 }
 
 
@@ -340,10 +770,14 @@ pub async fn finish_wifi(){
 async fn do_stop(){
     loop {
         if  let Some(WifiNetState::WifiConnected)  = *WIFI_STATE.lock().await {
-            if Instant::now().as_secs() - LAST_USE_TIME_SECS.lock().await.unwrap() > HOW_LONG_SECS_CLOSE {
+            let idle = LEASE_QUEUE.lock(|queue| {
+                let queue = queue.borrow();
+                queue.active == 0 && queue.waiting.is_empty()
+                    && Instant::now().as_secs() - queue.released_at > HOW_LONG_SECS_CLOSE
+            });
+            if idle {
                 println!("do_stop_wifi");
                 STOP_WIFI_SIGNAL.signal(());
-                finish_wifi().await;
             }
         }
         Timer::after(Duration::from_millis(3000)).await
@@ -393,8 +827,9 @@ pub async fn start_wifi_ap(spawner: &Spawner,
     )
         .unwrap();
 
-    let (wifi_ap_interface, mut controller) =
-        esp_wifi::wifi::new_with_mode(&init, wifi, WifiApDevice).unwrap();
+    //同时开启 STA 用于扫描附近的网络，不会连接
+    let (wifi_ap_interface, _wifi_interface, mut controller) =
+        esp_wifi::wifi::new_ap_sta(&init, wifi).unwrap();
 
     let seed = 1234;
     let ap_config = Config::ipv4_static(StaticConfigV4 {
@@ -451,25 +886,70 @@ async fn connection_wifi_ap(mut controller: WifiController<'static>) {
     println!("start connection task");
     println!("Device capabilities: {:?}", controller.get_capabilities());
     loop {
-        match esp_wifi::wifi::get_wifi_state() {
-            WifiState::ApStarted => {
-                // wait until we're no longer connected
-                controller.wait_for_event(WifiEvent::ApStop).await;
-                Timer::after(Duration::from_millis(5000)).await
-            }
-            _ => {}
-        }
         if !matches!(controller.is_started(), Ok(true)) {
-            let client_config = Configuration::AccessPoint(AccessPointConfiguration {
-                ssid: "esp-wifi".try_into().unwrap(),
-                password:String::from_str("123456789").unwrap(),
-                ..Default::default()
-            });
-            controller.set_configuration(&client_config).unwrap();
+            let config = Configuration::Mixed(ClientConfiguration::default(), ap_configuration());
+            controller.set_configuration(&config).unwrap();
             println!("Starting wifi");
             controller.start().await.unwrap();
             println!("Wifi started!");
         }
+
+        let ap_stop = controller.wait_for_event(WifiEvent::ApStop);
+        let retry = Timer::after(Duration::from_secs(AP_RETRY_SECS));
+        match select3(ap_stop, SCAN_REQUEST_SIGNAL.wait(), retry).await {
+            Either3::First(_) => {
+                Timer::after(Duration::from_millis(5000)).await
+            }
+            Either3::Second(_) => {
+                SCAN_REQUEST_SIGNAL.reset();
+                scan(&mut controller).await;
+            }
+            Either3::Third(_) => {
+                retry_saved_network(&mut controller).await;
+            }
+        }
+    }
+}
+
+fn ap_configuration()->AccessPointConfiguration{
+    AccessPointConfiguration {
+        ssid: "esp-wifi".try_into().unwrap(),
+        password:String::from_str("123456789").unwrap(),
+        ..Default::default()
+    }
+}
+
+//配网模式下扫描到已保存的网络时尝试连接，成功后重启回到 STA 模式
+async fn retry_saved_network(controller:&mut WifiController<'static>){
+    if WIFI_INFO.lock().await.as_ref().map_or(true, |v| v.networks.is_empty()) {
+        return;
+    }
+    scan(controller).await;
+    //只在扫描到时尝试
+    let Some((network,Some(_))) = choose_network().await else { return; };
+    println!("retry saved wifi {}...",network.ssid);
+    let config = Configuration::Mixed(client_configuration(&network), ap_configuration());
+    if let Err(e) = controller.set_configuration(&config) {
+        println!("配置失败：{:?}",e);
+        return;
+    }
+    match controller.connect().await {
+        Ok(_) => {
+            println!("wifi {} is available again, restart to sta mode",network.ssid);
+            if let Some(wifi_info) = WIFI_INFO.lock().await.as_mut() {
+                if !wifi_info.wifi_finish {
+                    wifi_info.wifi_finish = true;
+                    if let Err(e) = wifi_info.write() {
+                        println!("保存失败：{:?}",e);
+                        return;
+                    }
+                }
+            }
+            software_reset();
+        }
+        Err(e) => {
+            println!("retry wifi failed: {e:?}");
+        }
     }
 }
 
@@ -497,6 +977,7 @@ async fn dhcp_service(){
                 let mut tx_buffer = [0u8; TX_BUFFER_SIZE];
                 let mut udp_socket = UdpSocket::new(ap_stack, &mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
                 udp_socket.bind(67);
+                let mut server = DhcpServer::new();
 
                 // 无限循环处理消息
                 loop {
@@ -505,23 +986,23 @@ async fn dhcp_service(){
                     match udp_socket.recv_from(&mut buf).await {
                         Ok((n, src)) => {
                             println!("Received {} bytes from {}", n, src);
-                            println!("Received:{:?} ", buf );
-
-                            let mut msg = Message::new(buf).unwrap();
-                            println!("msg op_type:{:?}",msg.op().unwrap()) ;
-                            let options =  v4_options!(msg; MessageType required, ServerIdentifier, RequestedIpAddress);
-                            match options {
-                                Ok((msg_type,si,ria)) => {
-                                    println!("msg type:{:?}",msg_type) ;
-                                    if msg_type == dhcparse::dhcpv4::MessageType::DISCOVER {
-                                        send_dhcp_offer(&udp_socket, src ,&msg).await;
+
+                            let Ok(msg) = Message::new(&buf[..n]) else { continue; };
+                            let mut response = [0u8; DHCP_PACKET_SIZE];
+                            let reply = server.handle(&msg, Instant::now().as_secs(), &mut response);
+                            println!("dhcp request from {:02x?}, reply {:?}", msg.chaddr().ok().and_then(|v| v.get(..6)), reply);
+                            match reply {
+                                Some(DhcpReply::Broadcast) => {
+                                    if let Err(e) = udp_socket.send_to(&response, (Ipv4Address::BROADCAST, 68)).await {
+                                        println!("dhcp send error: {:?}", e);
                                     }
-                                    else if msg_type ==  dhcparse::dhcpv4::MessageType::REQUEST {
-                                        let ip_addr = Ipv4Addr::new(192, 168, 2, 2);
-                                        send_dhcp_ack(&udp_socket, src, &msg).await;
+                                }
+                                Some(DhcpReply::Unicast(addr)) => {
+                                    if let Err(e) = udp_socket.send_to(&response, (Ipv4Address::from_bytes(&addr), 68)).await {
+                                        println!("dhcp send error: {:?}", e);
                                     }
                                 }
-                                Err(_) => {}
+                                None => {}
                             }
 
                             //udp_socket.send_to(&buf[..n], src).await;
@@ -531,7 +1012,6 @@ async fn dhcp_service(){
                         }
                     }
 
-                    Timer::after(Duration::from_secs(1)).await;
                 }
             }
         }
@@ -540,84 +1020,6 @@ async fn dhcp_service(){
     }
 }
 
-async fn send_dhcp_offer(udp_socket: &UdpSocket<'_>, src_addr: IpEndpoint, receive_msg: &Message<[u8; 512]>) {
-    println!("send_dhcp_offer") ;
-    // 构造并发送 DHCP Offer 消息
-    let router_ip:&Addr = (&[192u8,168,2,1][..]).try_into().unwrap();
-    let submask:&Addr = (&[255u8,255,255,0][..]).try_into().unwrap();
-
-    let mut offer_message = [0u8; 512];
-
-    offer_message[2] = 6;
-
-    let mut msg = Encoder
-        .append_options([DhcpOption::MessageType(dhcparse::dhcpv4::MessageType::OFFER)])
-        .append_options([DhcpOption::Router(&[*router_ip])])
-        .append_options([DhcpOption::SubnetMask(submask)])
-        .append_options([DhcpOption::AddressLeaseTime(3600)])
-        .append_options([DhcpOption::ServerIdentifier(router_ip)])
-        .append_options([DhcpOption::DomainNameServer(&[*router_ip])])
-        .encode(&Message::default(), &mut offer_message).unwrap();
-    msg.set_op(dhcparse::dhcpv4::OpCode::BootReply);
-    msg.set_xid(receive_msg.xid());
-    msg.set_chaddr(receive_msg.chaddr().unwrap());
-
-
-    let temp :[u8;4] = [192,168,2,1];
-    let si_addr:&Addr = (&temp[..]).try_into().unwrap();
-    *msg.siaddr_mut() = *si_addr;
-
-
-    let temp :[u8;4] = [192,168,2,2];
-    let yi_addr:&Addr = (&temp[..]).try_into().unwrap();
-    *msg.yiaddr_mut() = *yi_addr;
-
-    offer_message[1] = 1;
-    println!("{:?}",&offer_message);
-
-
-    let broadcast = ( Ipv4Address::BROADCAST,68);
-    udp_socket.send_to(&offer_message, broadcast).await;
-}
-
-async fn send_dhcp_ack(udp_socket: & UdpSocket<'_>, src_addr: IpEndpoint, receive_msg: &Message<[u8; 512]>) {
-    println!("send_dhcp_ack") ;
-    // 构造并发送 DHCP Acknowledge 消息
-    let router_ip:&Addr = (&[192u8,168,2,1][..]).try_into().unwrap();
-    let submask:&Addr = (&[255u8,255,255,0][..]).try_into().unwrap();
-    let mut offer_message = [0u8; 512];
-    offer_message[1] = 1;
-    offer_message[2] = 6;
-
-    let mut msg = Encoder
-        .append_options([DhcpOption::MessageType(dhcparse::dhcpv4::MessageType::ACK)])
-        .append_options([DhcpOption::Router(&[*router_ip])])
-        .append_options([DhcpOption::SubnetMask(submask)])
-        .append_options([DhcpOption::AddressLeaseTime(3600)])
-        .append_options([DhcpOption::ServerIdentifier(router_ip)])
-        .append_options([DhcpOption::DomainNameServer(&[*router_ip])])
-        .encode(&Message::default(), &mut offer_message).unwrap();
-    msg.set_op(dhcparse::dhcpv4::OpCode::BootReply);
-    msg.set_xid(receive_msg.xid());
-    msg.set_chaddr(receive_msg.chaddr().unwrap());
-
-    let temp :[u8;4] = [192,168,2,1];
-    let si_addr:&Addr = (&temp[..]).try_into().unwrap();
-    *msg.siaddr_mut() = *si_addr;
-
-
-
-    let temp :[u8;4] = [192,168,2,2];
-    let yi_addr:&Addr = (&temp[..]).try_into().unwrap();
-    *msg.yiaddr_mut() = *yi_addr;
-
-    offer_message[1] = 1;
-    println!("{:?}",&offer_message);
-
-    let broadcast = ( Ipv4Address::BROADCAST,68);
-    udp_socket.send_to(&offer_message, broadcast).await;
-}
-
 //dns劫持服务
 #[embassy_executor::task]
 async fn dns_service(){
@@ -655,8 +1057,11 @@ async fn dns_service(){
                             println!("Dns Received {} bytes from {}", n, src);
                             println!("Dns Received:{:?} ", buf );
 
-                            let response = create_dns_response(LOCAL_IP,&buf[..n]);
-                            udp_socket.send_to(&response, src).await.expect("发送数据失败");
+                            if let Some(response) = create_dns_response(LOCAL_IP,&buf[..n]) {
+                                if let Err(e) = udp_socket.send_to(&response, src).await {
+                                    println!("发送数据失败：{:?}",e);
+                                }
+                            }
                             //break 'main_loop;
 
                         }
@@ -675,25 +1080,49 @@ async fn dns_service(){
     }
 }
 
-fn create_dns_response(ip:Ipv4Addr, request: &[u8]) -> Vec<u8, 512> {
+//构建 DNS 响应，所有 A 查询都指向本机，手机连接热点后检测联网时会打开配网页面
+//只回复第一个问题，其他类型的查询回复没有记录，不是查询的包忽略
+fn create_dns_response(ip:Ipv4Addr, request: &[u8]) -> Option<Vec<u8, 512>> {
+    const HEADER_SIZE:usize = 12;
+    const TYPE_A:u16 = 1;
+    const TYPE_ANY:u16 = 255;
+
+    if request.len() < HEADER_SIZE || request[2] & 0x80 != 0 || u16::from_be_bytes([request[4], request[5]]) == 0 {
+        return None;
+    }
+    //问题部分：以 0 结尾的域名，之后是类型和类别
+    let mut pos = HEADER_SIZE;
+    loop {
+        let len = *request.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xC0 != 0 {
+            return None;
+        }
+        pos += len + 1;
+    }
+    let question = request.get(HEADER_SIZE..pos + 4)?;
+    let qtype = u16::from_be_bytes([request[pos], request[pos + 1]]);
+    let answer = qtype == TYPE_A || qtype == TYPE_ANY;
+
     let mut response = Vec::new();
+    response.extend_from_slice(&request[0..2]).ok()?; // 复制 ID
+    response.extend_from_slice(&[0x80 | (request[2] & 0x01), 0x80]).ok()?; // 标志：响应，保留期望递归，支持递归，无错误
+    response.extend_from_slice(&[0x00, 0x01]).ok()?; // 问题数：1
+    response.extend_from_slice(&[0x00, answer as u8]).ok()?; // 答案数
+    response.extend_from_slice(&[0x00, 0x00]).ok()?; // 权威答案数：0
+    response.extend_from_slice(&[0x00, 0x00]).ok()?; // 附加记录数：0
+    response.extend_from_slice(question).ok()?; // 复制查询部分
+    if answer {
+        response.extend_from_slice(&[0xc0, 0x0c]).ok()?; // 指针到查询部分
+        response.extend_from_slice(&[0x00, 0x01]).ok()?; // 类型：A
+        response.extend_from_slice(&[0x00, 0x01]).ok()?; // 类别：IN
+        response.extend_from_slice(&[0x00, 0x00, 0x00, 0x3c]).ok()?; // TTL：60秒
+        response.extend_from_slice(&[0x00, 0x04]).ok()?; // 数据长度：4字节
+        response.extend_from_slice(&ip.octets()).ok()?; // IP 地址
+    }
 
-    // 构建简单的 DNS 响应，将所有请求重定向到 ESP32 的 IP 地址
-    // 假设 DNS 请求符合规范并且无错误处理
-
-    response.extend_from_slice(&request[0..2]).unwrap(); // 复制 ID
-    response.extend_from_slice(&[0x81, 0x80]).unwrap(); // 标志：响应，无错误
-    response.extend_from_slice(&request[4..6]).unwrap(); // 问题数
-    response.extend_from_slice(&[0x00, 0x01]).unwrap(); // 答案数：1
-    response.extend_from_slice(&[0x00, 0x00]).unwrap(); // 权威答案数：0
-    response.extend_from_slice(&[0x00, 0x00]).unwrap(); // 附加记录数：0
-    response.extend_from_slice(&request[12..]).unwrap(); // 复制查询部分
-    response.extend_from_slice(&[0xc0, 0x0c]).unwrap(); // 指针到查询部分
-    response.extend_from_slice(&[0x00, 0x01]).unwrap(); // 类型：A
-    response.extend_from_slice(&[0x00, 0x01]).unwrap(); // 类别：IN
-    response.extend_from_slice(&[0x00, 0x00, 0x00, 0x3c]).unwrap(); // TTL：60秒
-    response.extend_from_slice(&[0x00, 0x04]).unwrap(); // 数据长度：4字节
-    response.extend_from_slice(&ip.octets()).unwrap(); // IP 地址
-
-    response
-}
\ No newline at end of file
+    Some(response)
+}
@@ -23,7 +23,7 @@ use time::{Duration, OffsetDateTime, UtcOffset, Weekday};
 /*use crate::pages::init_page::InitPage;*/
 
 use crate::sleep::{get_rtc_ms, get_sleep_ms};
-use crate::wifi::{finish_wifi, use_wifi};
+use crate::wifi::use_wifi;
 
 
 const POOL_NTP_ADDR: &str = "cn.pool.ntp.org";
@@ -277,6 +277,17 @@ pub fn sync_time_success()->bool{
     }
 }
 
+//时间已同步且唤醒后已从 rtc 恢复，刚唤醒时时钟还是从 0 开始
+pub async fn clock_ready()->bool{
+    if !sync_time_success() {
+        return false;
+    }
+    match get_clock() {
+        Some(clock) => clock.now().await.unix_timestamp() as u64 >= unsafe{ CLOCK_SYNC_TIME_SECOND },
+        None => false,
+    }
+}
+
 pub async fn save_time_to_rtc(){
     unsafe {
         WHEN_SLEEP_TIME_TIMESTAMP = get_clock().unwrap().now().await.unix_timestamp() as u64;
@@ -311,12 +322,13 @@ pub async fn ntp_worker() {
         if get_clock().unwrap().now().await.unix_timestamp() as u64 - sync_time_second  > 3600
             ||  sync_time_second == 0 {
             match use_wifi().await {
-                Ok(stack) => {
+                Ok(lease) => {
                     println!("NTP Request");
                     //init_page.append_log("NTP Request").await;
-                    match ntp_request(stack, get_clock().unwrap()).await {
+                    let result = ntp_request(lease.stack(), get_clock().unwrap()).await;
+                    drop(lease);
+                    match result {
                         Err(_) => {
-                            finish_wifi().await;
                             println!("NTP error response");
                             if(err_times > 10){
                                 err_times = 0;
@@ -327,7 +339,6 @@ pub async fn ntp_worker() {
                             err_times +=1;
                         }
                         Ok(_) => {
-                            finish_wifi().await;
                             println!("NTP ok ?");
                             unsafe {
                                 CLOCK_SYNC_TIME_SECOND =  get_clock().unwrap().now().await.unix_timestamp() as u64;